@@ -162,10 +162,32 @@ export interface PastePreview {
 
 export type PastePreviewWithStar = PastePreview & { starred_at: number };
 
-export async function getPastes(id: string, options?: RequestOptions): Promise<ApiResponse<PastePreview[]>> {
-  return request<PastePreview[]>(RequestMethod.GET, `/users/${id}/pastes`, options);
+export interface Page<T> {
+  items: T[],
+  next_cursor: string | null,
+  prev_cursor: string | null,
+  total: number | null,
 }
 
-export async function getStarredPastes(id: string, options?: RequestOptions): Promise<ApiResponse<PastePreviewWithStar[]>> {
-  return request<PastePreviewWithStar[]>(RequestMethod.GET, `/users/${id}/stars`, options);
+export type PageCursor = { before?: string, after?: string };
+
+export type PasteListSort = 'created' | 'stars' | 'views' | 'starred_at';
+
+function cursorQuery({ before, after }: PageCursor, sort?: PasteListSort): string {
+  const params = new URLSearchParams();
+  if (sort != null) params.set('sort', sort);
+  if (before != null) params.set('before', before);
+  if (after != null) params.set('after', after);
+
+  const query = params.toString();
+  return query ? `?${query}` : '';
+}
+
+// Sorting by starred_at is only supported when listing stars
+export async function getPastes(id: string, cursor: PageCursor = {}, sort?: PasteListSort, options?: RequestOptions): Promise<ApiResponse<Page<PastePreview>>> {
+  return request<Page<PastePreview>>(RequestMethod.GET, `/users/${id}/pastes${cursorQuery(cursor, sort)}`, options);
+}
+
+export async function getStarredPastes(id: string, cursor: PageCursor = {}, sort?: PasteListSort, options?: RequestOptions): Promise<ApiResponse<Page<PastePreviewWithStar>>> {
+  return request<Page<PastePreviewWithStar>>(RequestMethod.GET, `/users/${id}/stars${cursorQuery(cursor, sort)}`, options);
 }
@@ -1,5 +1,5 @@
 import {GetServerSideProps} from "next";
-import {getPastes, getStarredPastes, getUser, type PastePreview as PastePreviewType, type PastePreviewWithStar} from "../../../api/api";
+import {getPastes, getStarredPastes, getUser, type Page, type PastePreview as PastePreviewType, type PastePreviewWithStar, type PasteListSort} from "../../../api/api";
 import {getIp} from "../../[id]";
 import {DEFAULT_AVATAR, User} from "../../../components/NavBar";
 import Head from "next/head";
@@ -11,14 +11,17 @@ import StarIcon from '../../../public/icon-star.svg';
 import GithubIcon from '../../../public/icon-github.svg';
 import CalendarIcon from '../../../public/icon-calendar.svg';
 import CopyIcon from '../../../public/icon-copy.svg';
-import {useCallback, useEffect, useState} from "react";
+import {useEffect, useState} from "react";
 import {humanizeDuration} from "../../../components/PasteInterface";
 import {toast} from "react-toastify";
 import {useRouter} from "next/router";
 import PastePreview from "../../../components/PastePreview";
 
-export const getServerSideProps: GetServerSideProps = async ({ params, req: { connection, cookies, headers } }) => {
+export const getServerSideProps: GetServerSideProps = async ({ params, query, req: { connection, cookies, headers } }) => {
   const { id, page } = params! as { id: string, page?: string };
+  const before = typeof query.before === 'string' ? query.before : undefined;
+  const after = typeof query.after === 'string' ? query.after : undefined;
+  const sort = typeof query.sort === 'string' ? query.sort as PasteListSort : undefined;
   const ip = getIp(connection, headers);
   const options = { cookies, headers: { 'x-real-ip': ip } };
   const [ userStatus, userData ] = await getUser(id, options);
@@ -33,10 +36,11 @@ export const getServerSideProps: GetServerSideProps = async ({ params, req: { co
   }
 
   const [ status, pastesData ] = page === 'stars'
-    ? await getStarredPastes(id, options)
-    : await getPastes(id, options);
+    ? await getStarredPastes(id, { before, after }, sort, options)
+    : await getPastes(id, { before, after }, sort, options);
 
-  if (status === 404) {
+  // 400 means the cursor or sort in the URL is invalid
+  if (status === 404 || status === 400) {
     return { notFound: true }
   }
   
@@ -45,8 +49,10 @@ export const getServerSideProps: GetServerSideProps = async ({ params, req: { co
     throw new Error(`HTTP Internal Server Error encountered! Message: ${pastesData.message}`)
   }
   
+  const { items, next_cursor, prev_cursor } = pastesData as Page<AnyPaste>;
+
   return {
-    props: { userData, pastesData },
+    props: { userData, pastesData: items, nextCursor: next_cursor, prevCursor: prev_cursor },
   }
 }
 
@@ -282,32 +288,28 @@ const PastesArea = styled.div`
   }
 `;
 
+const PageNavigation = styled.div`
+  display: flex;
+  justify-content: center;
+  margin: 8px 0;
+`;
+
 type AnyPaste = PastePreviewType | PastePreviewWithStar;
 
-export default function ViewUser({ userData, pastesData }: { userData: User, pastesData: AnyPaste[] }) {
+type ViewUserProps = {
+  userData: User,
+  pastesData: Page<AnyPaste>['items'],
+  nextCursor: string | null,
+  prevCursor: string | null,
+};
+
+export default function ViewUser({ userData, pastesData, nextCursor, prevCursor }: ViewUserProps) {
   let [githubData, setGithubData] = useState<any>();
   let router = useRouter();
   let page = router.query.page as string || 'pastes';
-  let [sortBy, setSortBy]= useState('last_modified');
-  
-  let sortFunction = useCallback(() => {
-    if (sortBy === 'stars') {
-      return (a: AnyPaste, b: AnyPaste) => {
-        return b.stars - a.stars;
-      }
-    } else if (sortBy === 'views') {
-      return (a: AnyPaste, b: AnyPaste) => {
-        return b.views - a.views;
-      }
-    } else if (sortBy === 'alphabetical') {
-      return (a: AnyPaste, b: AnyPaste) => {
-        return a.name.localeCompare(b.name);
-      }
-    }
-    return (a: AnyPaste, b: AnyPaste) => {
-      return b.created_at - a.created_at;
-    }
-  }, [sortBy]);
+  // Pages are sorted by the API, so changing the sort goes back to the first page
+  let sortBy = router.query.sort as PasteListSort || (page === 'stars' ? 'starred_at' : 'created');
+  let pageUrl = (cursor: string) => `/users/${userData.id}/${page}?sort=${sortBy}&${cursor}`;
 
   useEffect(() => {
     if (userData.github_id) {
@@ -399,26 +401,42 @@ export default function ViewUser({ userData, pastesData }: { userData: User, pas
                 Sort By:
               </span>
               <SortBySelect>
-                <select onChange={(e) => setSortBy(e.currentTarget.value)}>
-                  <option value="last_modified">Last Modified</option>
-                  <option value="created_at">Creation Date</option>
+                <select
+                  value={sortBy}
+                  onChange={async (e) => await router.push(`/users/${userData.id}/${page}?sort=${e.currentTarget.value}`)}
+                >
+                  {page === 'stars' && <option value="starred_at">Date Starred</option>}
+                  <option value="created">Creation Date</option>
                   <option value="stars">Stars</option>
                   <option value="views">Views</option>
-                  <option value="alphabetical">Alphabetical</option>
                 </select>
                 <span className="focus" />
               </SortBySelect>
             </div>
           </UserPastesHeader>
           <PastesArea>
-            {/* TODO: paginate this. definitely not scalable with many pastes */}
-            {pastesData
-              .sort(sortFunction())
-              .map(paste => (
-                <PastePreview data={paste} showAuthor={page === 'stars'} key={paste.id} />
-              ))
-            }
+            {pastesData.map(paste => (
+              <PastePreview data={paste} showAuthor={page === 'stars'} key={paste.id} />
+            ))}
           </PastesArea>
+          {(prevCursor || nextCursor) && (
+            <PageNavigation>
+              {prevCursor && (
+                <SwitchPageButton active={false} onClick={async () => await router.push(
+                  pageUrl(`before=${encodeURIComponent(prevCursor)}`)
+                )}>
+                  Previous
+                </SwitchPageButton>
+              )}
+              {nextCursor && (
+                <SwitchPageButton active={false} onClick={async () => await router.push(
+                  pageUrl(`after=${encodeURIComponent(nextCursor)}`)
+                )}>
+                  Next
+                </SwitchPageButton>
+              )}
+            </PageNavigation>
+          )}
         </UserPastesSection>
       </Container>
     </>
@@ -0,0 +1,512 @@
+//! A small token-frequency classifier for guessing the language of a file with no other clues.
+//!
+//! Every language has a list of tokens characteristic to it, each with a weight. Tokens made only
+//! of letters, digits and underscores are matched against the words of the content, while other
+//! tokens are matched as substrings. A language scores the sum of the weights of its tokens, each
+//! multiplied by the logarithm of how often the token occurs, so a few distinctive tokens beat many
+//! occurrences of a common one.
+
+use std::collections::HashMap;
+
+const TOKENS: &[(&str, &[(&str, f64)])] = &[
+    (
+        "Batchfile",
+        &[
+            ("@echo", 4.0),
+            ("goto", 1.5),
+            ("setlocal", 3.0),
+            ("%~dp0", 4.0),
+            ("REM", 1.5),
+        ],
+    ),
+    (
+        "C",
+        &[
+            ("#include", 2.0),
+            ("printf", 1.5),
+            ("malloc", 2.0),
+            ("free", 1.0),
+            ("struct", 1.0),
+            ("typedef", 1.5),
+            ("sizeof", 1.5),
+            ("NULL", 1.0),
+            (".h>", 2.0),
+            ("->", 0.5),
+        ],
+    ),
+    (
+        "C#",
+        &[
+            ("namespace", 1.5),
+            ("using System", 4.0),
+            ("Console.WriteLine", 4.0),
+            ("public", 0.5),
+            ("static", 0.5),
+            ("void", 0.5),
+            ("string", 0.5),
+            ("var", 0.5),
+            ("{ get;", 3.0),
+        ],
+    ),
+    (
+        "C++",
+        &[
+            ("#include", 1.5),
+            ("std::", 4.0),
+            ("cout", 3.0),
+            ("template", 2.0),
+            ("nullptr", 3.0),
+            ("namespace", 1.0),
+            ("virtual", 1.5),
+            ("auto", 0.5),
+            ("::", 0.5),
+        ],
+    ),
+    (
+        "CSS",
+        &[
+            ("px;", 2.0),
+            ("em;", 1.5),
+            ("color:", 1.5),
+            ("margin:", 2.0),
+            ("padding:", 2.0),
+            ("display:", 2.0),
+            ("font-", 1.5),
+            ("@media", 3.0),
+        ],
+    ),
+    (
+        "Diff",
+        &[
+            ("\n+++ ", 4.0),
+            ("\n--- ", 2.0),
+            ("\n@@ ", 4.0),
+            ("diff --git", 5.0),
+        ],
+    ),
+    (
+        "Dockerfile",
+        &[
+            ("FROM", 2.0),
+            ("RUN", 1.5),
+            ("COPY", 1.5),
+            ("WORKDIR", 3.0),
+            ("ENTRYPOINT", 3.0),
+            ("CMD", 1.0),
+            ("EXPOSE", 3.0),
+            ("ENV", 1.0),
+        ],
+    ),
+    (
+        "Elixir",
+        &[
+            ("defmodule", 5.0),
+            ("defp", 3.0),
+            ("def", 0.5),
+            ("do", 0.5),
+            ("end", 0.5),
+            ("|>", 2.0),
+            ("IO.puts", 4.0),
+        ],
+    ),
+    (
+        "Go",
+        &[
+            ("package", 1.5),
+            ("func", 2.0),
+            (":=", 2.0),
+            ("fmt", 2.5),
+            ("chan", 2.5),
+            ("go", 0.5),
+            ("defer", 2.5),
+            ("nil", 1.0),
+        ],
+    ),
+    (
+        "HTML",
+        &[
+            ("<!DOCTYPE", 5.0),
+            ("<html", 4.0),
+            ("<div", 2.5),
+            ("</", 1.0),
+            ("<head>", 3.0),
+            ("<body", 3.0),
+            ("href=", 1.5),
+            ("class=\"", 1.0),
+        ],
+    ),
+    (
+        "Haskell",
+        &[
+            ("module", 0.5),
+            ("where", 1.0),
+            ("::", 1.0),
+            ("->", 0.5),
+            ("<-", 1.0),
+            ("import qualified", 5.0),
+            ("putStrLn", 4.0),
+            ("data", 0.5),
+            ("instance", 1.0),
+            ("$", 0.3),
+        ],
+    ),
+    ("INI", &[("\n[", 1.0), ("=", 0.3)]),
+    (
+        "JSON",
+        &[
+            ("\": ", 1.5),
+            ("\":", 1.0),
+            ("null", 0.5),
+            ("true", 0.3),
+            ("false", 0.3),
+        ],
+    ),
+    (
+        "Java",
+        &[
+            ("public class", 3.0),
+            ("System.out.println", 5.0),
+            ("import java", 5.0),
+            ("extends", 1.0),
+            ("implements", 1.5),
+            ("private", 0.5),
+            ("public", 0.5),
+            ("static", 0.5),
+            ("void", 0.5),
+            ("@Override", 3.0),
+            ("new", 0.3),
+        ],
+    ),
+    (
+        "JavaScript",
+        &[
+            ("function", 1.0),
+            ("const", 0.5),
+            ("let", 0.5),
+            ("var", 0.5),
+            ("=>", 1.0),
+            ("console.log", 3.0),
+            ("require(", 2.5),
+            ("module.exports", 4.0),
+            ("document.", 3.0),
+            ("===", 1.5),
+            ("undefined", 1.5),
+        ],
+    ),
+    (
+        "Kotlin",
+        &[
+            ("fun", 2.5),
+            ("val", 1.5),
+            ("var", 0.5),
+            ("println", 1.0),
+            ("data class", 4.0),
+            ("companion", 4.0),
+            ("?.", 1.0),
+            ("when", 1.0),
+        ],
+    ),
+    (
+        "Lua",
+        &[
+            ("local", 2.0),
+            ("function", 0.5),
+            ("end", 1.0),
+            ("then", 1.0),
+            ("elseif", 3.0),
+            ("~=", 3.0),
+            ("..", 0.5),
+            ("nil", 1.0),
+            ("require", 0.5),
+        ],
+    ),
+    (
+        "Makefile",
+        &[
+            ("\n\t", 1.0),
+            (".PHONY", 5.0),
+            ("$(", 1.5),
+            ("$@", 4.0),
+            (":=", 0.5),
+            ("all:", 3.0),
+        ],
+    ),
+    (
+        "Markdown",
+        &[
+            ("\n# ", 2.0),
+            ("\n## ", 2.5),
+            ("```", 3.0),
+            ("](", 2.0),
+            ("\n- ", 1.0),
+            ("\n* ", 1.0),
+            ("**", 1.0),
+        ],
+    ),
+    (
+        "PHP",
+        &[
+            ("<?php", 10.0),
+            ("$this->", 4.0),
+            ("echo", 1.0),
+            ("function", 0.5),
+            ("->", 0.5),
+            ("=>", 0.5),
+            ("namespace", 0.5),
+        ],
+    ),
+    (
+        "Perl",
+        &[
+            ("my", 1.5),
+            ("use strict", 5.0),
+            ("sub", 1.5),
+            ("=~", 2.0),
+            ("print", 0.5),
+            ("@_", 4.0),
+            ("$_", 1.5),
+        ],
+    ),
+    (
+        "PowerShell",
+        &[
+            ("Write-Host", 5.0),
+            ("param(", 2.0),
+            ("$PSScriptRoot", 5.0),
+            ("Get-", 2.5),
+            ("Set-", 2.0),
+            ("-eq", 2.0),
+            ("-ne", 2.0),
+        ],
+    ),
+    (
+        "Python",
+        &[
+            ("def", 1.5),
+            ("import", 0.5),
+            ("self", 1.5),
+            ("elif", 3.0),
+            ("None", 1.5),
+            ("True", 1.0),
+            ("False", 1.0),
+            ("print(", 0.5),
+            ("__init__", 4.0),
+            ("lambda", 1.0),
+            ("):\n", 2.0),
+        ],
+    ),
+    (
+        "Ruby",
+        &[
+            ("def", 1.0),
+            ("end", 1.0),
+            ("puts", 2.5),
+            ("require", 1.0),
+            ("attr_accessor", 5.0),
+            ("do |", 3.0),
+            ("elsif", 3.0),
+            ("nil", 0.5),
+            ("module", 0.5),
+        ],
+    ),
+    (
+        "Rust",
+        &[
+            ("fn", 2.0),
+            ("let", 0.5),
+            ("mut", 2.5),
+            ("impl", 2.0),
+            ("pub", 1.0),
+            ("use", 0.5),
+            ("match", 0.5),
+            ("::", 0.5),
+            ("->", 0.5),
+            ("&self", 3.0),
+            ("println!", 4.0),
+            ("Some(", 1.5),
+            ("Ok(", 1.5),
+            ("#[derive(", 5.0),
+        ],
+    ),
+    (
+        "SQL",
+        &[
+            ("SELECT", 2.0),
+            ("FROM", 1.0),
+            ("WHERE", 1.5),
+            ("INSERT INTO", 4.0),
+            ("CREATE TABLE", 5.0),
+            ("JOIN", 1.5),
+            ("select", 1.0),
+            ("from", 0.3),
+            ("where", 0.5),
+        ],
+    ),
+    (
+        "Scala",
+        &[
+            ("object", 1.0),
+            ("def", 1.0),
+            ("val", 1.5),
+            ("case class", 5.0),
+            ("extends", 0.5),
+            ("trait", 1.5),
+            ("=>", 0.5),
+            ("implicit", 3.0),
+        ],
+    ),
+    (
+        "Shell",
+        &[
+            ("echo", 1.5),
+            ("fi", 2.5),
+            ("then", 1.0),
+            ("esac", 4.0),
+            ("done", 1.5),
+            ("export", 1.0),
+            ("$1", 1.5),
+            ("${", 1.0),
+            ("[[ ", 2.0),
+            (" && ", 0.5),
+        ],
+    ),
+    (
+        "TOML",
+        &[
+            ("\n[", 1.0),
+            (" = \"", 1.5),
+            ("[[", 1.0),
+            ("[package]", 5.0),
+            ("[dependencies]", 5.0),
+        ],
+    ),
+    (
+        "TypeScript",
+        &[
+            ("interface", 2.0),
+            ("const", 0.5),
+            ("=>", 0.5),
+            (": string", 4.0),
+            (": number", 4.0),
+            (": boolean", 4.0),
+            ("export", 1.0),
+            ("import", 0.5),
+            ("readonly", 2.5),
+            ("implements", 0.5),
+        ],
+    ),
+    (
+        "XML",
+        &[("<?xml", 10.0), ("</", 1.0), ("xmlns", 3.0), ("/>", 0.5)],
+    ),
+    (
+        "YAML",
+        &[("\n- ", 1.0), (": ", 0.5), ("---", 1.0), (":\n  ", 2.0)],
+    ),
+];
+
+/// Content shorter than this many bytes is too short to classify reliably.
+const MIN_CONTENT_LEN: usize = 16;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Scores the content against every language in the classifier accepted by `filter`, returning the
+/// best language along with a confidence between 0 and 1.
+pub fn classify(content: &str, filter: impl Fn(&str) -> bool) -> Option<(&'static str, f64)> {
+    if content.trim().len() < MIN_CONTENT_LEN {
+        return None;
+    }
+
+    let mut words = HashMap::<&str, usize>::new();
+    for word in content.split(|c: char| !is_word_char(c)) {
+        if !word.is_empty() {
+            *words.entry(word).or_default() += 1;
+        }
+    }
+
+    // Prefix a newline so tokens anchored to the start of a line also match the first line
+    let content = format!("\n{}", content);
+
+    let mut scores = TOKENS
+        .iter()
+        .filter(|(language, _)| filter(language))
+        .map(|(language, tokens)| {
+            let score = tokens
+                .iter()
+                .map(|(token, weight)| {
+                    let count = if token.chars().all(is_word_char) {
+                        words.get(token).copied().unwrap_or_default()
+                    } else {
+                        content.matches(token).count()
+                    };
+
+                    weight * (count as f64).ln_1p()
+                })
+                .sum::<f64>();
+
+            (*language, score)
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .collect::<Vec<_>>();
+
+    scores.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    let (language, best) = *scores.first()?;
+    let runner_up = scores.get(1).map_or(0.0, |(_, score)| *score);
+
+    // How far ahead of the runner-up the best language is, scaled down when the content has
+    // few tokens of the best language at all
+    let margin = (best - runner_up) / best;
+    let evidence = 1.0 - (-best / 8.0).exp();
+
+    Some((language, margin * evidence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const C: &str = "#include <stdio.h>\n#include <stdlib.h>\n\nint main(void) {\n    char *s = malloc(sizeof(char) * 4);\n    printf(\"%p\\n\", s);\n    free(s);\n    return 0;\n}\n";
+
+    #[test]
+    fn short_content_is_not_classified() {
+        assert_eq!(classify("fmt.Println(x)", |_| true), None);
+        assert_eq!(classify("   \n\n    x := 1    \n\n", |_| true), None);
+    }
+
+    #[test]
+    fn content_without_tokens_is_not_classified() {
+        assert_eq!(
+            classify("the quick brown fox jumps over the lazy dog", |_| true),
+            None
+        );
+    }
+
+    #[test]
+    fn distinctive_content_is_classified_confidently() {
+        let (language, confidence) = classify(C, |_| true).unwrap();
+
+        assert_eq!(language, "C");
+        assert!(confidence > 0.5, "{}", confidence);
+    }
+
+    #[test]
+    fn ambiguous_content_has_low_confidence() {
+        // Both C and C++ use #include, with only the header extension tipping it towards C
+        let (language, confidence) =
+            classify("#include <a.h>\n#include <b.h>\n", |_| true).unwrap();
+
+        assert_eq!(language, "C");
+        assert!(confidence < 0.3, "{}", confidence);
+    }
+
+    #[test]
+    fn filtered_out_languages_are_not_considered() {
+        assert_eq!(classify(C, |language| language == "Go"), None);
+
+        let (language, _) = classify(C, |language| language == "C++").unwrap();
+        assert_eq!(language, "C++");
+    }
+}
@@ -0,0 +1,200 @@
+//! Generates source code describing the languages of a [`Registry`].
+
+use crate::{Language, Registry};
+
+use std::io::{self, Write};
+
+const HEADER: &str =
+    "Generated by the Turbine linguist tool from GitHub Linguist's languages.yml. Do not edit.";
+
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).expect("strings always serialize")
+}
+
+/// Writes a TypeScript declaration file with the types of `languages.json` and `extensions.json`.
+pub fn write_typescript(registry: &Registry, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "// {}", HEADER)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "export type LanguageName =")?;
+    if registry.is_empty() {
+        writeln!(writer, "  never")?;
+    }
+    for language in registry.iter() {
+        writeln!(writer, "  | {}", json_string(&language.name))?;
+    }
+    writeln!(writer, "  ;")?;
+    writeln!(writer)?;
+
+    writer.write_all(
+        br#"export interface Language {
+  type: "data" | "programming" | "markup" | "prose";
+  name: LanguageName;
+  color: string;
+  aliases: string[];
+  filenames: string[];
+  extensions: string[];
+  interpreters: string[];
+  wrap: boolean;
+  ace_mode: string;
+  codemirror_mode: string | null;
+  codemirror_mime_type: string | null;
+  tm_scope: string | null;
+  group: string | null;
+  language_id: number | null;
+}
+
+/** The contents of languages.json. */
+export type Languages = Record<LanguageName, Language>;
+
+/** The contents of extensions.json, mapping lowercase extensions to the language that owns them. */
+export type ExtensionIndex = Record<string, LanguageName>;
+"#,
+    )
+}
+
+fn rust_str_slice(items: &[String]) -> String {
+    let items = items
+        .iter()
+        .map(|item| format!("{:?}", item))
+        .collect::<Vec<_>>();
+
+    format!("&[{}]", items.join(", "))
+}
+
+fn rust_option<T: std::fmt::Debug>(value: Option<T>) -> String {
+    match value {
+        Some(value) => format!("Some({:?})", value),
+        None => "None".to_string(),
+    }
+}
+
+fn write_rust_language(writer: &mut impl Write, language: &Language) -> io::Result<()> {
+    writeln!(writer, "    Language {{")?;
+    writeln!(writer, "        name: {:?},", language.name)?;
+    writeln!(writer, "        r#type: {:?},", language.r#type)?;
+    writeln!(writer, "        color: {:?},", language.color)?;
+    writeln!(
+        writer,
+        "        aliases: {},",
+        rust_str_slice(&language.aliases)
+    )?;
+    writeln!(
+        writer,
+        "        filenames: {},",
+        rust_str_slice(&language.filenames)
+    )?;
+    writeln!(
+        writer,
+        "        extensions: {},",
+        rust_str_slice(&language.extensions)
+    )?;
+    writeln!(
+        writer,
+        "        interpreters: {},",
+        rust_str_slice(&language.interpreters)
+    )?;
+    writeln!(writer, "        wrap: {},", language.wrap)?;
+    writeln!(writer, "        ace_mode: {:?},", language.ace_mode)?;
+    writeln!(
+        writer,
+        "        codemirror_mode: {},",
+        rust_option(language.codemirror_mode.as_deref())
+    )?;
+    writeln!(
+        writer,
+        "        codemirror_mime_type: {},",
+        rust_option(language.codemirror_mime_type.as_deref())
+    )?;
+    writeln!(
+        writer,
+        "        tm_scope: {},",
+        rust_option(language.tm_scope.as_deref())
+    )?;
+    writeln!(
+        writer,
+        "        group: {},",
+        rust_option(language.group.as_deref())
+    )?;
+    writeln!(
+        writer,
+        "        language_id: {},",
+        rust_option(language.language_id)
+    )?;
+    writeln!(writer, "    }},")
+}
+
+/// Writes a self-contained Rust module holding every language in a static, sorted slice, along with
+/// an extension index and functions to look up both with a binary search.
+pub fn write_rust(registry: &Registry, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "//! {}", HEADER)?;
+    writer.write_all(
+        br#"
+/// A language as it appears in the generated `languages.json`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Language {
+    pub name: &'static str,
+    pub r#type: &'static str,
+    pub color: &'static str,
+    pub aliases: &'static [&'static str],
+    pub filenames: &'static [&'static str],
+    pub extensions: &'static [&'static str],
+    pub interpreters: &'static [&'static str],
+    pub wrap: bool,
+    pub ace_mode: &'static str,
+    pub codemirror_mode: Option<&'static str>,
+    pub codemirror_mime_type: Option<&'static str>,
+    pub tm_scope: Option<&'static str>,
+    pub group: Option<&'static str>,
+    pub language_id: Option<u64>,
+}
+
+/// Looks up a language by its exact canonical name.
+pub fn get(name: &str) -> Option<&'static Language> {
+    LANGUAGES
+        .binary_search_by(|language| language.name.cmp(name))
+        .ok()
+        .map(|idx| &LANGUAGES[idx])
+}
+
+/// Looks up the language that owns an extension, which must be lowercase and include the leading
+/// dot.
+pub fn find_by_extension(extension: &str) -> Option<&'static Language> {
+    EXTENSIONS
+        .binary_search_by(|(ext, _)| (*ext).cmp(extension))
+        .ok()
+        .map(|idx| &LANGUAGES[EXTENSIONS[idx].1])
+}
+
+/// Every language, sorted by name.
+pub static LANGUAGES: &[Language] = &[
+"#,
+    )?;
+
+    for language in registry.iter() {
+        write_rust_language(&mut writer, language)?;
+    }
+    writeln!(writer, "];")?;
+    writeln!(writer)?;
+
+    writeln!(
+        writer,
+        "/// Lowercase extensions mapped to the index of the language in [`LANGUAGES`] that owns them,"
+    )?;
+    writeln!(writer, "/// sorted by extension.")?;
+    writeln!(writer, "pub static EXTENSIONS: &[(&str, usize)] = &[")?;
+
+    let names = registry
+        .iter()
+        .map(|language| language.name.as_str())
+        .collect::<Vec<_>>();
+
+    for (extension, name) in registry.extension_index() {
+        let idx = names
+            .binary_search(&name)
+            .expect("extension owners should be in the registry");
+
+        writeln!(writer, "    ({:?}, {}),", extension, idx)?;
+    }
+    writeln!(writer, "];")
+}
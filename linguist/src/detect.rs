@@ -0,0 +1,319 @@
+use crate::{classifier, heuristics, Language, Registry};
+
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// How the language of a file was detected, from most to least reliable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectionMethod {
+    /// An Emacs or Vim modeline naming the language, such as `# vim: ft=python`.
+    Modeline,
+    /// The exact filename, such as `Dockerfile`.
+    Filename,
+    /// The interpreter in the shebang line, such as `#!/usr/bin/env python3`.
+    Shebang,
+    /// An extension claimed by a single language.
+    Extension,
+    /// An extension claimed by several languages, narrowed down by patterns in the content.
+    Heuristics,
+    /// The frequency of tokens characteristic to the language.
+    Classifier,
+}
+
+/// The language detected for a file. See [`Registry::detect_file`].
+#[derive(Clone, Copy, Debug)]
+pub struct Detection<'a> {
+    pub language: &'a Language,
+    pub method: DetectionMethod,
+    /// How likely the detected language is to be correct, between 0 and 1.
+    pub confidence: f64,
+}
+
+/// Modelines are only looked for in this many lines at the start and end of the content.
+const MODELINE_SEARCH_LINES: usize = 5;
+
+static MODELINES: OnceLock<[Regex; 3]> = OnceLock::new();
+
+fn modelines() -> &'static [Regex; 3] {
+    MODELINES.get_or_init(|| {
+        [
+            // Emacs: -*- mode: ruby; tab-width: 2 -*-
+            Regex::new(r"(?i)-\*-.*?\bmode\s*:\s*([\w+#.-]+).*?-\*-").unwrap(),
+            // Emacs: -*- ruby -*-
+            Regex::new(r"-\*-\s*([\w+#.-]+)\s*-\*-").unwrap(),
+            // Vim: vim: set ft=ruby: or vi: syntax=ruby
+            Regex::new(r"(?i)(?:^|\s)(?:vi|vim|ex)(?:[<=>]?\d+)?:.*?\b(?:ft|filetype|syntax)\s*=\s*([\w+#.-]+)")
+                .unwrap(),
+        ]
+    })
+}
+
+fn modeline_language(content: &str) -> Option<&str> {
+    let lines = content.lines().collect::<Vec<_>>();
+    let tail = lines.len().saturating_sub(MODELINE_SEARCH_LINES);
+
+    lines
+        .iter()
+        .take(MODELINE_SEARCH_LINES)
+        .chain(lines.iter().skip(tail.max(MODELINE_SEARCH_LINES)))
+        .find_map(|line| {
+            modelines()
+                .iter()
+                .find_map(|modeline| modeline.captures(line))
+                .and_then(|captures| captures.get(1))
+                .map(|name| name.as_str())
+        })
+}
+
+/// The interpreter of a shebang line, such as `python3` for `#!/usr/bin/env -S python3 -u`.
+fn shebang_interpreter(content: &str) -> Option<&str> {
+    let mut parts = content
+        .lines()
+        .next()?
+        .strip_prefix("#!")?
+        .split_whitespace();
+    let interpreter = parts.next()?.rsplit('/').next()?;
+
+    if interpreter == "env" {
+        // Skip options and environment variables given to env
+        parts.find(|part| !part.starts_with('-') && !part.contains('='))
+    } else {
+        Some(interpreter)
+    }
+}
+
+impl Registry {
+    /// Looks up a language by an interpreter, falling back to the interpreter without its version,
+    /// so that `python3.11` finds Python through `python3` or `python`. Names and aliases are also
+    /// tried, since `languages.json` generated by older versions has no interpreters.
+    fn find_by_versioned_interpreter(&self, interpreter: &str) -> Option<&Language> {
+        let without_minor = interpreter
+            .rsplit_once('.')
+            .filter(|(_, minor)| minor.chars().all(|c| c.is_ascii_digit()))
+            .map_or(interpreter, |(major, _)| major);
+        let without_version =
+            interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+        [interpreter, without_minor, without_version]
+            .into_iter()
+            .find_map(|interpreter| self.find_by_interpreter(interpreter))
+            .or_else(|| {
+                [interpreter, without_minor, without_version]
+                    .into_iter()
+                    .find_map(|interpreter| self.find(interpreter))
+            })
+    }
+
+    /// Detects the language of a file from its filename, if it has one, and its content.
+    ///
+    /// Modelines are trusted first, followed by the exact filename, the shebang and the extension.
+    /// Extensions claimed by several languages are narrowed down with heuristics ported from GitHub
+    /// Linguist and then with a token-frequency classifier, which is also used as a last resort
+    /// when nothing else matches.
+    pub fn detect_file(&self, filename: Option<&str>, content: &str) -> Option<Detection<'_>> {
+        let detection = |language, method, confidence| Detection {
+            language,
+            method,
+            confidence,
+        };
+
+        if let Some(language) = modeline_language(content).and_then(|name| self.find(name)) {
+            return Some(detection(language, DetectionMethod::Modeline, 1.0));
+        }
+
+        let filename = filename
+            .map(|path| path.rsplit(['/', '\\']).next().unwrap_or(path))
+            .filter(|filename| !filename.is_empty());
+
+        if let Some(language) = filename.and_then(|filename| self.find_by_filename(filename)) {
+            return Some(detection(language, DetectionMethod::Filename, 1.0));
+        }
+
+        if let Some(language) =
+            shebang_interpreter(content).and_then(|name| self.find_by_versioned_interpreter(name))
+        {
+            return Some(detection(language, DetectionMethod::Shebang, 0.95));
+        }
+
+        let extension = match filename.and_then(|filename| self.longest_extension(filename)) {
+            Some(extension) => extension,
+            None => {
+                return classifier::classify(content, |name| self.get(name).is_some()).map(
+                    |(name, confidence)| {
+                        detection(
+                            self.get(name).expect("classified languages should exist"),
+                            DetectionMethod::Classifier,
+                            confidence,
+                        )
+                    },
+                )
+            }
+        };
+
+        let candidates = self
+            .find_all_by_extension(&extension)
+            .map(|language| language.name.as_str())
+            .collect::<Vec<_>>();
+
+        if let [name] = candidates[..] {
+            return self
+                .get(name)
+                .map(|language| detection(language, DetectionMethod::Extension, 0.95));
+        }
+
+        if let Some(name) = heuristics::disambiguate(&extension, content, &candidates) {
+            return self
+                .get(name)
+                .map(|language| detection(language, DetectionMethod::Heuristics, 0.9));
+        }
+
+        if let Some((name, confidence)) =
+            classifier::classify(content, |name| candidates.contains(&name))
+        {
+            return self.get(name).map(|language| {
+                detection(
+                    language,
+                    DetectionMethod::Classifier,
+                    // The extension already narrowed it down to a few languages
+                    0.5 + confidence / 2.0,
+                )
+            });
+        }
+
+        // Fall back to the language the extension ranks highest for
+        let candidate_count = candidates.len() as f64;
+        self.find_by_extension(&extension)
+            .map(|language| detection(language, DetectionMethod::Extension, 1.0 / candidate_count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::test_language;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    fn registry() -> Registry {
+        Registry::new([
+            Language {
+                extensions: strings(&[".py", ".shared"]),
+                interpreters: strings(&["python", "python3"]),
+                ..test_language("Python")
+            },
+            Language {
+                filenames: strings(&["Rakefile"]),
+                extensions: strings(&[".rb"]),
+                interpreters: strings(&["ruby"]),
+                ..test_language("Ruby")
+            },
+            Language {
+                extensions: strings(&[".go", ".shared"]),
+                ..test_language("Go")
+            },
+            Language {
+                extensions: strings(&[".c", ".h"]),
+                ..test_language("C")
+            },
+            Language {
+                extensions: strings(&[".m", ".h"]),
+                ..test_language("Objective-C")
+            },
+        ])
+    }
+
+    fn detect(filename: Option<&str>, content: &str) -> (String, DetectionMethod, f64) {
+        let registry = registry();
+        let detection = registry.detect_file(filename, content).unwrap();
+
+        (
+            detection.language.name.clone(),
+            detection.method,
+            detection.confidence,
+        )
+    }
+
+    #[test]
+    fn modelines_come_first() {
+        let (name, method, _) = detect(
+            Some("Rakefile"),
+            "#!/usr/bin/env python3\n# vim: set ft=go:\n",
+        );
+        assert_eq!((name.as_str(), method), ("Go", DetectionMethod::Modeline));
+
+        let (name, _, _) = detect(Some("main.go"), "# -*- mode: ruby; tab-width: 2 -*-\n");
+        assert_eq!(name, "Ruby");
+    }
+
+    #[test]
+    fn filenames_come_before_shebangs() {
+        let (name, method, _) = detect(Some("tasks/Rakefile"), "#!/usr/bin/env python3\n");
+        assert_eq!((name.as_str(), method), ("Ruby", DetectionMethod::Filename));
+    }
+
+    #[test]
+    fn shebangs_come_before_extensions() {
+        let (name, method, _) = detect(Some("script.rb"), "#!/usr/bin/env -S python3.11 -u\n");
+        assert_eq!(
+            (name.as_str(), method),
+            ("Python", DetectionMethod::Shebang)
+        );
+
+        let (name, _, _) = detect(Some("script.py"), "#!/usr/local/bin/ruby\n");
+        assert_eq!(name, "Ruby");
+    }
+
+    #[test]
+    fn extensions_come_before_heuristics() {
+        let (name, method, confidence) = detect(Some("main.py"), "print('hi')\n");
+        assert_eq!(
+            (name.as_str(), method),
+            ("Python", DetectionMethod::Extension)
+        );
+        assert_eq!(confidence, 0.95);
+    }
+
+    #[test]
+    fn heuristics_narrow_down_shared_extensions() {
+        let (name, method, _) = detect(Some("view.h"), "@interface View : NSObject\n@end\n");
+        assert_eq!(
+            (name.as_str(), method),
+            ("Objective-C", DetectionMethod::Heuristics),
+        );
+
+        let (name, method, _) = detect(Some("util.h"), "int add(int a, int b);\n");
+        assert_eq!((name.as_str(), method), ("C", DetectionMethod::Heuristics));
+    }
+
+    #[test]
+    fn classifier_breaks_ties_between_shared_extensions() {
+        let go = "package main\n\nimport \"fmt\"\n\nfunc main() {\n\tx := 1\n\tfmt.Println(x)\n}\n";
+        let (name, method, confidence) = detect(Some("main.shared"), go);
+        assert_eq!((name.as_str(), method), ("Go", DetectionMethod::Classifier));
+        assert!(confidence > 0.5);
+
+        // Too short to classify, so the extension's highest ranked language is a coin toss. Neither
+        // language has it as its primary extension, so the tie goes to Go alphabetically.
+        let (name, method, confidence) = detect(Some("main.shared"), "x");
+        assert_eq!((name.as_str(), method), ("Go", DetectionMethod::Extension));
+        assert_eq!(confidence, 0.5);
+    }
+
+    #[test]
+    fn classifier_is_the_last_resort() {
+        let go = "package main\n\nfunc main() {\n\tx := 1\n\tfmt.Println(x)\n}\n";
+        let (name, method, _) = detect(None, go);
+        assert_eq!((name.as_str(), method), ("Go", DetectionMethod::Classifier));
+
+        let (name, method, _) = detect(Some("README"), go);
+        assert_eq!((name.as_str(), method), ("Go", DetectionMethod::Classifier));
+
+        assert!(registry()
+            .detect_file(Some("notes"), "just some words")
+            .is_none());
+    }
+}
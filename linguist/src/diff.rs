@@ -0,0 +1,259 @@
+use crate::Registry;
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+
+/// A change of the language that owns an extension. See [`Registry::find_by_extension`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtensionChange {
+    /// The extension in lowercase, including the leading dot.
+    pub extension: String,
+    /// The language that used to own the extension, if any.
+    pub old: Option<String>,
+    /// The language that owns the extension now, if any.
+    pub new: Option<String>,
+}
+
+/// The differences between two registries, usually an old and a newly generated `languages.json`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegistryDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Old and new names of languages that were renamed. A language is considered renamed when
+    /// it kept its Linguist language ID, or when its old name became an alias of an added language.
+    pub renamed: Vec<(String, String)>,
+    /// Extensions that changed owners, excluding owners that were only renamed.
+    pub extensions: Vec<ExtensionChange>,
+}
+
+impl RegistryDiff {
+    /// Compares an old registry against a new one.
+    pub fn new(old: &Registry, new: &Registry) -> Self {
+        let old_names = old
+            .iter()
+            .map(|language| language.name.as_str())
+            .collect::<BTreeSet<_>>();
+        let new_names = new
+            .iter()
+            .map(|language| language.name.as_str())
+            .collect::<BTreeSet<_>>();
+
+        let mut removed = old_names
+            .difference(&new_names)
+            .copied()
+            .collect::<Vec<_>>();
+        let mut added = new_names
+            .difference(&old_names)
+            .copied()
+            .collect::<Vec<_>>();
+
+        let new_ids = new
+            .iter()
+            .filter(|language| added.contains(&language.name.as_str()))
+            .filter_map(|language| Some((language.language_id?, language.name.as_str())))
+            .collect::<HashMap<_, _>>();
+
+        let mut renamed = Vec::new();
+        removed.retain(|&name| {
+            let language = old
+                .get(name)
+                .expect("removed languages should be in the old registry");
+            let rename = language
+                .language_id
+                .and_then(|id| new_ids.get(&id).copied())
+                .or_else(|| {
+                    new.find_by_alias(name)
+                        .map(|language| language.name.as_str())
+                        .filter(|new_name| added.contains(new_name))
+                });
+
+            match rename {
+                Some(new_name) => {
+                    renamed.push((name.to_string(), new_name.to_string()));
+                    false
+                }
+                None => true,
+            }
+        });
+        added.retain(|&name| !renamed.iter().any(|(_, new_name)| new_name == name));
+
+        let renames = renamed
+            .iter()
+            .map(|(old_name, new_name)| (old_name.as_str(), new_name.as_str()))
+            .collect::<HashMap<_, _>>();
+        let old_index = old.extension_index();
+        let new_index = new.extension_index();
+
+        let extensions = old_index
+            .keys()
+            .chain(new_index.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|&extension| {
+                let old_owner = old_index.get(extension).copied();
+                let new_owner = new_index.get(extension).copied();
+
+                let renamed_owner =
+                    old_owner.map(|owner| renames.get(owner).copied().unwrap_or(owner));
+                (renamed_owner != new_owner).then(|| ExtensionChange {
+                    extension: extension.to_string(),
+                    old: old_owner.map(ToString::to_string),
+                    new: new_owner.map(ToString::to_string),
+                })
+            })
+            .collect();
+
+        Self {
+            added: added.into_iter().map(ToString::to_string).collect(),
+            removed: removed.into_iter().map(ToString::to_string).collect(),
+            renamed,
+            extensions,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.extensions.is_empty()
+    }
+
+    /// Whether any language name stopped existing, either by being removed or renamed. Stored
+    /// languages with such names no longer match a language.
+    pub fn removes_names(&self) -> bool {
+        !self.removed.is_empty() || !self.renamed.is_empty()
+    }
+
+    /// Extensions that changed owners, grouped by their old and new owner.
+    fn grouped_extensions(&self) -> BTreeMap<(Option<&str>, Option<&str>), Vec<&str>> {
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+
+        for change in &self.extensions {
+            groups
+                .entry((change.old.as_deref(), change.new.as_deref()))
+                .or_default()
+                .push(change.extension.as_str());
+        }
+        groups
+    }
+}
+
+impl fmt::Display for RegistryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} added, {} removed, {} renamed, {} extensions changed owners",
+            self.added.len(),
+            self.removed.len(),
+            self.renamed.len(),
+            self.extensions.len(),
+        )?;
+
+        for name in &self.added {
+            writeln!(f, "  + {}", name)?;
+        }
+        for name in &self.removed {
+            writeln!(f, "  - {}", name)?;
+        }
+        for (old, new) in &self.renamed {
+            writeln!(f, "  ~ {} -> {}", old, new)?;
+        }
+        for ((old, new), extensions) in self.grouped_extensions() {
+            writeln!(
+                f,
+                "  {}: {} -> {}",
+                extensions.join(", "),
+                old.unwrap_or("(none)"),
+                new.unwrap_or("(none)"),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{language::test_language, Language};
+
+    fn language(name: &str, id: Option<u64>, aliases: &[&str], extensions: &[&str]) -> Language {
+        Language {
+            language_id: id,
+            aliases: aliases.iter().map(ToString::to_string).collect(),
+            extensions: extensions.iter().map(ToString::to_string).collect(),
+            ..test_language(name)
+        }
+    }
+
+    #[test]
+    fn detects_renames_by_language_id_and_alias() {
+        let old = Registry::new([
+            language("Kept", Some(1), &[], &[".kept"]),
+            language("Old ID", Some(2), &[], &[".id"]),
+            language("Old Alias", None, &[], &[".alias"]),
+            language("Removed", Some(3), &[], &[".removed"]),
+        ]);
+        let new = Registry::new([
+            language("Kept", Some(1), &[], &[".kept"]),
+            language("New ID", Some(2), &[], &[".id"]),
+            language("New Alias", None, &["old alias"], &[".alias"]),
+            language("Added", Some(4), &[], &[".added"]),
+        ]);
+
+        let diff = RegistryDiff::new(&old, &new);
+
+        assert_eq!(diff.added, ["Added"]);
+        assert_eq!(diff.removed, ["Removed"]);
+        assert_eq!(
+            diff.renamed,
+            [
+                ("Old Alias".to_string(), "New Alias".to_string()),
+                ("Old ID".to_string(), "New ID".to_string()),
+            ],
+        );
+        // Extensions of renamed languages didn't change owners
+        assert_eq!(
+            diff.extensions,
+            [
+                ExtensionChange {
+                    extension: ".added".to_string(),
+                    old: None,
+                    new: Some("Added".to_string()),
+                },
+                ExtensionChange {
+                    extension: ".removed".to_string(),
+                    old: Some("Removed".to_string()),
+                    new: None,
+                },
+            ],
+        );
+        assert!(diff.removes_names());
+    }
+
+    #[test]
+    fn aliases_of_existing_languages_are_not_renames() {
+        let old = Registry::new([
+            language("Gone", None, &[], &[]),
+            language("Kept", None, &[], &[]),
+        ]);
+        let new = Registry::new([language("Kept", None, &["gone"], &[])]);
+
+        let diff = RegistryDiff::new(&old, &new);
+
+        assert_eq!(diff.removed, ["Gone"]);
+        assert!(diff.renamed.is_empty());
+    }
+
+    #[test]
+    fn identical_registries_have_no_differences() {
+        let registry = Registry::new([language("Kept", Some(1), &[], &[".kept"])]);
+
+        let diff = RegistryDiff::new(&registry, &registry);
+
+        assert!(diff.is_empty());
+        assert!(!diff.removes_names());
+    }
+}
@@ -0,0 +1,212 @@
+//! Disambiguates extensions claimed by several languages by the content of the file, ported from
+//! a subset of GitHub Linguist's `heuristics.yml`.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// A rule picks its language when any of its patterns match, or always when it has no patterns.
+struct Rule {
+    language: &'static str,
+    patterns: &'static [&'static str],
+}
+
+struct Disambiguation {
+    extensions: &'static [&'static str],
+    rules: &'static [Rule],
+}
+
+const fn rule(language: &'static str, patterns: &'static [&'static str]) -> Rule {
+    Rule { language, patterns }
+}
+
+const OBJECTIVE_C: &str = r#"^\s*(@(interface|class|protocol|property|end|synchronised|selector|implementation)\b|#import\s+.+\.h[">])"#;
+const CPP: &[&str] = &[
+    r"^\s*#\s*include <(cstdint|string|vector|map|list|array|bitset|queue|stack|forward_list|unordered_map|unordered_set|(i|o|io)stream)>",
+    r"^\s*template\s*<",
+    r"^[ \t]*(try|constexpr)",
+    r"^[ \t]*catch\s*\(",
+    r"^[ \t]*(class|(using[ \t]+)?namespace)\s+\w+",
+    r"^[ \t]*(private|public|protected):$",
+    r"std::\w+",
+];
+const PERL: &str = r"\buse\s+(?:strict\b|v?5\b)";
+const RAKU: &str = r"^\s*(?:use\s+v6\b|\bmodule\b|\b(?:my\s+)?class\b)";
+
+const DISAMBIGUATIONS: &[Disambiguation] = &[
+    Disambiguation {
+        extensions: &[".cs"],
+        rules: &[
+            rule("Smalltalk", &[r"![\w\s]+methodsFor: "]),
+            rule(
+                "C#",
+                &[r"^\s*(using\s+[A-Z][\s\w.]+;|namespace\s*[\w\.]+\s*(\{|;)|//)"],
+            ),
+        ],
+    },
+    Disambiguation {
+        extensions: &[".h"],
+        rules: &[
+            rule("Objective-C", &[OBJECTIVE_C]),
+            rule("C++", CPP),
+            rule("C", &[]),
+        ],
+    },
+    Disambiguation {
+        extensions: &[".inc"],
+        rules: &[
+            rule("PHP", &[r"^<\?(?:php)?"]),
+            rule("POV-Ray SDL", &[r"^\s*#(declare|local|macro|while)\s"]),
+        ],
+    },
+    Disambiguation {
+        extensions: &[".m"],
+        rules: &[
+            rule("Objective-C", &[OBJECTIVE_C]),
+            rule("Mercury", &[r":- module"]),
+            rule("MUF", &[r"^: "]),
+            rule("M", &[r"^\s*;"]),
+            rule("Mathematica", &[r"\(\*", r"\*\)$"]),
+            rule("MATLAB", &[r"^\s*%"]),
+            rule("Limbo", &[r"^\w+\s*:\s*module\s*\{"]),
+        ],
+    },
+    Disambiguation {
+        extensions: &[".md"],
+        rules: &[
+            rule("Markdown", &[r"(^[-A-Za-z0-9=#!\*\[|>])|</", r"\A\z"]),
+            rule("GCC Machine Description", &[r"^(;;|\(define_)"]),
+        ],
+    },
+    Disambiguation {
+        extensions: &[".pl"],
+        rules: &[
+            rule("Prolog", &[r"^[^#]*:-"]),
+            rule("Perl", &[PERL]),
+            rule("Raku", &[RAKU]),
+        ],
+    },
+    Disambiguation {
+        extensions: &[".pm"],
+        rules: &[rule("Perl", &[PERL]), rule("Raku", &[RAKU])],
+    },
+    Disambiguation {
+        extensions: &[".rs"],
+        rules: &[
+            rule("Rust", &[r"^(use |fn |mod |pub |macro_rules|impl|#!?\[)"]),
+            rule(
+                "RenderScript",
+                &[r"#include|#pragma\s+(rs|version)|__attribute__"],
+            ),
+            rule("XML", &[r"^\s*<\?xml"]),
+        ],
+    },
+    Disambiguation {
+        extensions: &[".sql"],
+        rules: &[
+            rule(
+                "PLpgSQL",
+                &[r"(?i)^\\i\b|AS\s+\$\$|LANGUAGE\s+'?plpgsql'?|BEGIN(\s+WORK)?\s*;"],
+            ),
+            rule(
+                "SQLPL",
+                &[
+                    r"(?i)(alter module)|(language sql)|(begin( NOT)+ atomic)",
+                    r"(?i)signal SQLSTATE '[0-9]+'",
+                ],
+            ),
+            rule(
+                "PLSQL",
+                &[
+                    r"(?i)\$\$PLSQL_|XMLTYPE|systimestamp|\.nextval|CONNECT\s+BY|AUTHID\s+(DEFINER|CURRENT_USER)|constructor\W+function",
+                ],
+            ),
+            rule(
+                "TSQL",
+                &[r"(?i)^\s*GO\b|BEGIN(\s+TRY|\s+CATCH)|OUTPUT\s+INSERTED|DECLARE\s+@|\[dbo\]"],
+            ),
+            rule("SQL", &[]),
+        ],
+    },
+    Disambiguation {
+        extensions: &[".ts"],
+        rules: &[rule("XML", &[r"<TS\b"]), rule("TypeScript", &[])],
+    },
+    Disambiguation {
+        extensions: &[".v"],
+        rules: &[
+            rule(
+                "Coq",
+                &[r"(?:^|\s)(?:Proof|Qed)\.(?:$|\s)|(?:^|\s)Require[ \t]+(Import|Export)\s"],
+            ),
+            rule(
+                "Verilog",
+                &[
+                    r"^[ \t]*module\s+[^\s()]+\s+\#?\(|^[ \t]*`(?:define|ifdef|ifndef|include|timescale)|^[ \t]*always[ \t]+@|^[ \t]*initial[ \t]+(begin|@)",
+                ],
+            ),
+            rule(
+                "V",
+                &[r"\$(?:if|else)[ \t]|^[ \t]*fn\s+[^\s()]+\(.*?\).*?\{|^[ \t]*for\s*\{"],
+            ),
+        ],
+    },
+];
+
+struct CompiledRule {
+    language: &'static str,
+    patterns: Vec<Regex>,
+}
+
+static COMPILED: OnceLock<Vec<(&'static [&'static str], Vec<CompiledRule>)>> = OnceLock::new();
+
+fn compiled() -> &'static [(&'static [&'static str], Vec<CompiledRule>)] {
+    COMPILED.get_or_init(|| {
+        DISAMBIGUATIONS
+            .iter()
+            .map(|disambiguation| {
+                let rules = disambiguation
+                    .rules
+                    .iter()
+                    .map(|rule| CompiledRule {
+                        language: rule.language,
+                        patterns: rule
+                            .patterns
+                            .iter()
+                            // Linguist's patterns are matched line by line
+                            .map(|pattern| {
+                                Regex::new(&format!("(?m){}", pattern))
+                                    .expect("heuristic patterns should be valid")
+                            })
+                            .collect(),
+                    })
+                    .collect();
+
+                (disambiguation.extensions, rules)
+            })
+            .collect()
+    })
+}
+
+/// Picks one of the candidate languages claiming an extension by the content of a file. Rules for
+/// languages that are not candidates are skipped.
+pub fn disambiguate<'a>(extension: &str, content: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let (_, rules) = compiled()
+        .iter()
+        .find(|(extensions, _)| extensions.contains(&extension))?;
+
+    rules
+        .iter()
+        .filter(|rule| {
+            rule.patterns.is_empty()
+                || rule
+                    .patterns
+                    .iter()
+                    .any(|pattern| pattern.is_match(content))
+        })
+        .find_map(|rule| {
+            candidates
+                .iter()
+                .find(|&&candidate| candidate == rule.language)
+                .copied()
+        })
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+fn default_color() -> String {
+    "#FFFFFF".to_string()
+}
+
+/// A language as it appears in GitHub Linguist's `languages.yml`. The name of the language is the
+/// key it is stored under.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RawLanguage {
+    pub r#type: String,
+    #[serde(default = "default_color")]
+    pub color: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub filenames: Vec<String>,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub interpreters: Vec<String>,
+    #[serde(default)]
+    pub wrap: bool,
+    pub ace_mode: String,
+    pub codemirror_mode: Option<String>,
+    pub codemirror_mime_type: Option<String>,
+    pub tm_scope: Option<String>,
+    pub group: Option<String>,
+    pub language_id: Option<u64>,
+}
+
+/// A language as it appears in the generated `languages.json`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Language {
+    /// One of `data`, `programming`, `markup` or `prose`.
+    pub r#type: String,
+    /// The canonical name of the language, for example `Python`.
+    pub name: String,
+    /// The color of the language as a hex code, for example `#3572A5`.
+    pub color: String,
+    /// Alternative names of the language, in lowercase.
+    pub aliases: Vec<String>,
+    /// Exact filenames that belong to the language, such as `Dockerfile`.
+    pub filenames: Vec<String>,
+    /// Extensions that belong to the language including the leading dot, with the primary
+    /// extension first.
+    pub extensions: Vec<String>,
+    /// Interpreters that run the language, as found in shebangs, such as `python3`.
+    #[serde(default)]
+    pub interpreters: Vec<String>,
+    /// Whether long lines should be soft-wrapped when displaying the language.
+    pub wrap: bool,
+    /// The Ace editor mode used to highlight the language.
+    pub ace_mode: String,
+    /// The CodeMirror mode used to highlight the language, if CodeMirror supports it.
+    #[serde(default)]
+    pub codemirror_mode: Option<String>,
+    /// The MIME type CodeMirror uses for the language, for example `text/x-rustsrc`.
+    #[serde(default)]
+    pub codemirror_mime_type: Option<String>,
+    /// The TextMate scope used to highlight the language, for example `source.rust`.
+    #[serde(default)]
+    pub tm_scope: Option<String>,
+    /// The name of the language this language is grouped under, for example `JavaScript` for JSX.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// The unique and stable ID GitHub Linguist gives the language.
+    #[serde(default)]
+    pub language_id: Option<u64>,
+}
+
+impl Language {
+    pub fn from_raw(name: String, raw: RawLanguage) -> Self {
+        let RawLanguage {
+            r#type,
+            color,
+            aliases,
+            filenames,
+            extensions,
+            interpreters,
+            wrap,
+            ace_mode,
+            codemirror_mode,
+            codemirror_mime_type,
+            tm_scope,
+            group,
+            language_id,
+        } = raw;
+
+        Self {
+            r#type,
+            name,
+            color,
+            aliases,
+            filenames,
+            extensions,
+            interpreters,
+            wrap,
+            ace_mode,
+            codemirror_mode,
+            codemirror_mime_type,
+            tm_scope,
+            group,
+            language_id,
+        }
+    }
+
+    /// The primary extension of the language, if it has any.
+    pub fn primary_extension(&self) -> Option<&str> {
+        self.extensions.first().map(String::as_str)
+    }
+}
+
+/// A programming language with the given name and nothing else, to be filled in by tests.
+#[cfg(test)]
+pub(crate) fn test_language(name: &str) -> Language {
+    Language {
+        r#type: "programming".to_string(),
+        name: name.to_string(),
+        color: default_color(),
+        aliases: Vec::new(),
+        filenames: Vec::new(),
+        extensions: Vec::new(),
+        interpreters: Vec::new(),
+        wrap: false,
+        ace_mode: name.to_lowercase(),
+        codemirror_mode: None,
+        codemirror_mime_type: None,
+        tm_scope: None,
+        group: None,
+        language_id: None,
+    }
+}
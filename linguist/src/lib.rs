@@ -0,0 +1,21 @@
+//! Language data from [GitHub Linguist](https://github.com/github/linguist), as used by Turbine.
+//!
+//! The `linguist` binary converts Linguist's `languages.yml` into the `languages.json` file shipped
+//! with the frontend, which can be loaded back into a [`Registry`] with [`Registry::from_json`].
+
+mod classifier;
+pub mod codegen;
+mod detect;
+mod diff;
+mod heuristics;
+mod language;
+mod overrides;
+mod registry;
+
+pub use detect::{Detection, DetectionMethod};
+pub use diff::{ExtensionChange, RegistryDiff};
+pub use language::{Language, RawLanguage};
+pub use overrides::{AppliedOverride, Override, OverrideReport, Overrides};
+#[cfg(feature = "yaml")]
+pub use registry::parse_languages_yml;
+pub use registry::{EditorMode, Registry};
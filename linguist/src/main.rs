@@ -1,64 +1,268 @@
-use std::collections::HashMap;
-
-use hyper::{Client, Uri};
+use hyper::{body::Body, client::HttpConnector, Client, Request, Uri};
 use hyper_tls::HttpsConnector;
-use serde::{Deserialize, Serialize};
-use serde_json;
-use serde_yaml;
+use linguist::{codegen, parse_languages_yml, EditorMode, Overrides, Registry, RegistryDiff};
+use serde::Serialize;
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+const REPOSITORY: &str = "github/linguist";
+const DEFAULT_REF: &str = "master";
+// Embedded so that the binary doesn't depend on where it is run from or where it was built
+const DEFAULT_OVERRIDES: &str = include_str!("../overrides.yml");
+
+const USAGE: &str = "\
+Usage: linguist [OPTIONS] [OUTPUT_DIR]
+
+Converts GitHub Linguist's languages.yml into languages.json, written to OUTPUT_DIR
+(defaults to ../frontend/public) along with extensions.json, which maps every extension to the
+language that owns it.
 
-const URL: &'static str =
-    "https://raw.githubusercontent.com/github/linguist/master/lib/linguist/languages.yml";
+Options:
+  --input <PATH>  Read languages.yml from PATH instead of downloading it, or from stdin if PATH is -
+  --ref <REF>     The linguist tag, branch or commit to download languages.yml from, or that the
+                  file given to --input was taken from (defaults to master when downloading)
+  --overrides <PATH>
+                  Apply the overrides in PATH to languages.yml (defaults to this crate's
+                  overrides.yml, as it was when the binary was built)
+  --no-overrides  Do not apply any overrides
+  --editor-mode <ace|codemirror|none>
+                  Drop languages that this editor only highlights as plain text, or keep every
+                  language if none (defaults to ace)
+  --pretty        Pretty-print languages.json
+  --typescript <PATH>
+                  Also write a TypeScript declaration of languages.json and extensions.json to PATH
+  --rust <PATH>   Also write a Rust module holding the languages in static, sorted slices to PATH
+  --diff <PATH>   Instead of writing anything, compare the languages against an older languages.json
+                  at PATH and print the languages added, removed and renamed, and the extensions that
+                  changed owners. Fails if any language was removed or renamed
+  --allow-removals
+                  Do not fail --diff when languages were removed or renamed
+  -h, --help      Print this message";
 
-fn default_color() -> String {
-    "#FFFFFF".to_string()
+#[derive(Debug, Default)]
+struct Args {
+    input: Option<String>,
+    r#ref: Option<String>,
+    overrides: Option<String>,
+    no_overrides: bool,
+    editor_mode: Option<EditorMode>,
+    pretty: bool,
+    typescript: Option<PathBuf>,
+    rust: Option<PathBuf>,
+    diff: Option<String>,
+    allow_removals: bool,
+    output_dir: Option<PathBuf>,
 }
 
-#[derive(Deserialize, Debug)]
-struct RawEntry {
-    r#type: String,
-    #[serde(default = "default_color")]
-    color: String,
-    #[serde(default)]
-    aliases: Vec<String>,
-    #[serde(default)]
-    filenames: Vec<String>,
-    #[serde(default)]
-    extensions: Vec<String>,
-    #[serde(default)]
-    wrap: bool,
-    ace_mode: String,
+impl Args {
+    fn parse() -> Result<Self, Box<dyn Error>> {
+        let mut args = Self {
+            editor_mode: Some(EditorMode::Ace),
+            ..Self::default()
+        };
+        let mut argv = std::env::args().skip(1);
+
+        while let Some(arg) = argv.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                "--input" => args.input = Some(argv.next().ok_or("--input requires a path")?),
+                "--ref" => {
+                    let r#ref = argv
+                        .next()
+                        .ok_or("--ref requires a tag, branch or commit")?;
+
+                    // The ref becomes part of a URL, so keep it to characters git allows anyway
+                    if r#ref.is_empty()
+                        || !r#ref.chars().all(|c| {
+                            c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/')
+                        })
+                    {
+                        return Err(format!("invalid ref {:?}", r#ref).into());
+                    }
+                    args.r#ref = Some(r#ref);
+                }
+                "--overrides" => {
+                    args.overrides = Some(argv.next().ok_or("--overrides requires a path")?);
+                }
+                "--no-overrides" => args.no_overrides = true,
+                "--editor-mode" => {
+                    args.editor_mode = match argv
+                        .next()
+                        .ok_or("--editor-mode requires an editor")?
+                        .as_str()
+                    {
+                        "none" => None,
+                        mode => Some(mode.parse()?),
+                    };
+                }
+                "--pretty" => args.pretty = true,
+                "--typescript" => {
+                    args.typescript =
+                        Some(argv.next().ok_or("--typescript requires a path")?.into());
+                }
+                "--rust" => args.rust = Some(argv.next().ok_or("--rust requires a path")?.into()),
+                "--diff" => args.diff = Some(argv.next().ok_or("--diff requires a path")?),
+                "--allow-removals" => args.allow_removals = true,
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(format!("unknown option {}\n\n{}", arg, USAGE).into());
+                }
+                _ if args.output_dir.is_none() => args.output_dir = Some(arg.into()),
+                _ => return Err(format!("unexpected argument {}\n\n{}", arg, USAGE).into()),
+            }
+        }
+
+        if args.no_overrides && args.overrides.is_some() {
+            return Err("--overrides and --no-overrides cannot be used together".into());
+        }
+
+        Ok(args)
+    }
 }
 
-#[derive(Serialize, Debug)]
-struct Entry {
-    r#type: String,
-    name: String,
-    color: String,
-    aliases: Vec<String>,
-    filenames: Vec<String>,
-    extensions: Vec<String>,
-    wrap: bool,
-    ace_mode: String,
+/// Where the languages.yml that languages.json was generated from came from. This is written to
+/// languages.meta.json next to languages.json.
+#[derive(Debug, Serialize)]
+struct Metadata {
+    /// The URL or path languages.yml was read from, or `-` for stdin.
+    source: String,
+    /// The linguist commit languages.yml was downloaded from, or the ref given alongside --input.
+    revision: Option<String>,
 }
 
-async fn load_raw_yaml() -> Vec<u8> {
-    let https = HttpsConnector::new();
+type HttpsClient = Client<HttpsConnector<HttpConnector>>;
+
+async fn get(client: &HttpsClient, uri: &str, accept: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let request = Request::get(uri.parse::<Uri>()?)
+        .header("Accept", accept)
+        // Required by the GitHub API
+        .header("User-Agent", "turbine-linguist")
+        .body(Body::empty())?;
 
-    let response = Client::builder()
-        .build::<_, hyper::Body>(https)
-        .get(Uri::from_static(URL))
-        .await
-        .expect("http error");
+    let response = client.request(request).await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response).await?.to_vec();
 
-    hyper::body::to_bytes(response)
-        .await
-        .expect("could not read bytes")
-        .to_vec()
+    if !status.is_success() {
+        return Err(format!("GET {} failed with {}", uri, status).into());
+    }
+
+    Ok(body)
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let output_dir = match std::env::args().skip(1).next().map(|p| p.into()) {
+/// Resolves a tag, branch or abbreviated commit of linguist into a full commit hash, so that
+/// languages.yml is downloaded from exactly the commit that is recorded.
+async fn resolve_commit(client: &HttpsClient, r#ref: &str) -> Result<String, Box<dyn Error>> {
+    if r#ref.len() == 40 && r#ref.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(r#ref.to_string());
+    }
+
+    let uri = format!(
+        "https://api.github.com/repos/{}/commits/{}",
+        REPOSITORY, r#ref
+    );
+    let sha = get(client, &uri, "application/vnd.github.sha").await?;
+
+    Ok(String::from_utf8(sha)?.trim().to_string())
+}
+
+async fn download_raw_yaml(r#ref: &str) -> Result<(Vec<u8>, Metadata), Box<dyn Error>> {
+    let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+    let commit = resolve_commit(&client, r#ref).await?;
+
+    let url = format!(
+        "https://raw.githubusercontent.com/{}/{}/lib/linguist/languages.yml",
+        REPOSITORY, commit,
+    );
+    let yaml = get(&client, &url, "text/plain").await?;
+
+    Ok((
+        yaml,
+        Metadata {
+            source: url,
+            revision: Some(commit),
+        },
+    ))
+}
+
+fn read_raw_yaml(path: &str, r#ref: Option<String>) -> Result<(Vec<u8>, Metadata), Box<dyn Error>> {
+    let yaml = if path == "-" {
+        let mut buffer = Vec::new();
+        std::io::stdin().read_to_end(&mut buffer)?;
+        buffer
+    } else {
+        std::fs::read(path).map_err(|e| format!("could not read {}: {}", path, e))?
+    };
+
+    Ok((
+        yaml,
+        Metadata {
+            source: path.to_string(),
+            revision: r#ref,
+        },
+    ))
+}
+
+fn read_overrides(path: &str) -> Result<Overrides, Box<dyn Error>> {
+    let yaml = std::fs::read(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+
+    Ok(Overrides::from_yaml(&yaml).map_err(|e| format!("could not parse {}: {}", path, e))?)
+}
+
+async fn run() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse()?;
+
+    let (yaml, metadata) = match args.input {
+        Some(path) => read_raw_yaml(&path, args.r#ref)?,
+        None => download_raw_yaml(args.r#ref.as_deref().unwrap_or(DEFAULT_REF)).await?,
+    };
+    let overrides = if args.no_overrides {
+        Overrides::default()
+    } else {
+        match args.overrides {
+            Some(path) => read_overrides(&path)?,
+            None => Overrides::from_yaml(DEFAULT_OVERRIDES.as_bytes())
+                .map_err(|e| format!("could not parse the default overrides: {}", e))?,
+        }
+    };
+
+    let mut languages = parse_languages_yml(&yaml)?;
+    let report = overrides.apply(&mut languages);
+    // The report goes to standard error so that standard output stays quiet
+    if !args.no_overrides {
+        eprint!("{}", report);
+    }
+
+    let registry = Registry::from_raw(languages, args.editor_mode);
+
+    if let Some(path) = args.diff {
+        let old_json = std::fs::read_to_string(&path)
+            .map_err(|e| format!("could not read {}: {}", path, e))?;
+        let diff = RegistryDiff::new(&Registry::from_json(&old_json)?, &registry);
+        print!("{}", diff);
+
+        if diff.removes_names() && !args.allow_removals {
+            return Err(
+                "languages were removed or renamed, which orphans files stored with their old \
+                 names (pass --allow-removals to ignore this)"
+                    .into(),
+            );
+        }
+        return Ok(());
+    }
+
+    // Resolve the other output paths before changing directories
+    let cwd = std::env::current_dir()?;
+    let typescript = args.typescript.map(|path| cwd.join(path));
+    let rust = args.rust.map(|path| cwd.join(path));
+
+    let output_dir = match args.output_dir {
         Some(p) => p,
         None => std::env::current_dir()?
             .parent()
@@ -68,68 +272,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
     std::env::set_current_dir(output_dir)?;
 
-    let data = serde_yaml::from_slice::<HashMap<String, RawEntry>>(&*load_raw_yaml().await)?;
-
-    let mut resolved = HashMap::new();
+    let mut file = BufWriter::new(File::create("languages.json")?);
+    if args.pretty {
+        registry.to_json_writer_pretty(&mut file)?;
+    } else {
+        registry.to_json_writer(&mut file)?;
+    }
+    file.flush()?;
 
-    for (
-        name,
-        RawEntry {
-            r#type,
-            aliases,
-            mut ace_mode,
-            color,
-            mut extensions,
-            filenames,
-            wrap,
-        },
-    ) in data
-    {
-        // Special case: remove extension .ts/.tsx/.rs to prioritize TypeScript/TSX/Rust language
-        if name == "XML" {
-            extensions.retain(|ext| ext != ".ts" && ext != ".tsx" && ext != ".rs");
-        }
-        // Special case: prioritize .md for Markdown instead of GCC Machine Description
-        else if name == "GCC Machine Description" {
-            continue;
-        }
-        // Special case: use the TSX ace mode instead of the default JavaScript
-        else if name == "TSX" {
-            ace_mode = "tsx".to_string();
-        }
-        // Special case: use the Kotlin ace mode instead of text
-        else if name == "Kotlin" {
-            ace_mode = "kotlin".to_string();
-        }
+    let mut file = BufWriter::new(File::create("extensions.json")?);
+    serde_json::to_writer(&mut file, &registry.extension_index())?;
+    file.flush()?;
+    serde_json::to_writer_pretty(File::create("languages.meta.json")?, &metadata)?;
 
-        // Remove anything that can give a false assumption to the user that
-        // the language is supported, even though it really is just text
-        if ace_mode == "text" && name != "Text" {
-            continue;
-        }
-
-        resolved.insert(
-            name.clone(),
-            Entry {
-                name,
-                aliases,
-                r#type,
-                ace_mode,
-                color,
-                extensions,
-                filenames,
-                wrap,
-            },
-        );
+    if let Some(path) = typescript {
+        let mut file = BufWriter::new(File::create(path)?);
+        codegen::write_typescript(&registry, &mut file)?;
+        file.flush()?;
+    }
+    if let Some(path) = rust {
+        let mut file = BufWriter::new(File::create(path)?);
+        codegen::write_rust(&registry, &mut file)?;
+        file.flush()?;
     }
-
-    serde_json::to_writer(
-        tokio::fs::File::create("languages.json")
-            .await?
-            .into_std()
-            .await,
-        &resolved,
-    )?;
 
     Ok(())
 }
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run().await {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
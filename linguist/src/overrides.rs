@@ -0,0 +1,239 @@
+use crate::RawLanguage;
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt};
+
+/// Changes to make to a single language of `languages.yml`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    /// Removes the language entirely.
+    #[serde(default)]
+    pub drop: bool,
+    /// Extensions to remove from the language, including the leading dot.
+    #[serde(default)]
+    pub remove_extensions: Vec<String>,
+    pub ace_mode: Option<String>,
+    pub color: Option<String>,
+    #[serde(default)]
+    pub add_aliases: Vec<String>,
+}
+
+/// A set of [`Override`]s keyed by the name of the language they apply to, usually loaded from
+/// `overrides.yml`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Overrides(pub BTreeMap<String, Override>);
+
+/// What applying a single [`Override`] did.
+#[derive(Clone, Debug)]
+pub struct AppliedOverride {
+    pub language: String,
+    pub changes: Vec<String>,
+    /// Parts of the override that no longer do anything, for example because the extension it
+    /// removes is already gone upstream.
+    pub warnings: Vec<String>,
+}
+
+/// The outcome of [`Overrides::apply`].
+#[derive(Clone, Debug, Default)]
+pub struct OverrideReport {
+    pub applied: Vec<AppliedOverride>,
+    /// Overrides for languages that don't exist.
+    pub unmatched: Vec<String>,
+}
+
+impl Overrides {
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &[u8]) -> serde_yaml::Result<Self> {
+        serde_yaml::from_slice(yaml)
+    }
+
+    /// Applies every override to the languages it names, reporting what changed.
+    pub fn apply(&self, languages: &mut BTreeMap<String, RawLanguage>) -> OverrideReport {
+        let mut report = OverrideReport::default();
+
+        for (name, rule) in &self.0 {
+            if rule.drop {
+                match languages.remove(name) {
+                    Some(_) => report.applied.push(AppliedOverride {
+                        language: name.clone(),
+                        changes: vec!["dropped".to_string()],
+                        warnings: Vec::new(),
+                    }),
+                    None => report.unmatched.push(name.clone()),
+                }
+                continue;
+            }
+
+            let language = match languages.get_mut(name) {
+                Some(language) => language,
+                None => {
+                    report.unmatched.push(name.clone());
+                    continue;
+                }
+            };
+            let mut applied = AppliedOverride {
+                language: name.clone(),
+                changes: Vec::new(),
+                warnings: Vec::new(),
+            };
+
+            for extension in &rule.remove_extensions {
+                let len = language.extensions.len();
+                language.extensions.retain(|ext| ext != extension);
+
+                if language.extensions.len() < len {
+                    applied
+                        .changes
+                        .push(format!("removed extension {}", extension));
+                } else {
+                    applied
+                        .warnings
+                        .push(format!("extension {} is not present", extension));
+                }
+            }
+
+            for (field, value, target) in [
+                ("ace_mode", &rule.ace_mode, &mut language.ace_mode),
+                ("color", &rule.color, &mut language.color),
+            ] {
+                if let Some(value) = value {
+                    if target == value {
+                        applied
+                            .warnings
+                            .push(format!("{} is already {}", field, value));
+                    } else {
+                        applied
+                            .changes
+                            .push(format!("set {} from {} to {}", field, target, value));
+                        *target = value.clone();
+                    }
+                }
+            }
+
+            for alias in &rule.add_aliases {
+                if language.aliases.contains(alias) {
+                    applied
+                        .warnings
+                        .push(format!("alias {} is already present", alias));
+                } else {
+                    applied.changes.push(format!("added alias {}", alias));
+                    language.aliases.push(alias.clone());
+                }
+            }
+
+            report.applied.push(applied);
+        }
+
+        report
+    }
+}
+
+impl fmt::Display for OverrideReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} overrides matched, {} unmatched",
+            self.applied.len(),
+            self.unmatched.len(),
+        )?;
+
+        for applied in &self.applied {
+            for change in &applied.changes {
+                writeln!(f, "  {}: {}", applied.language, change)?;
+            }
+            for warning in &applied.warnings {
+                writeln!(f, "  warning: {}: {}", applied.language, warning)?;
+            }
+        }
+
+        for name in &self.unmatched {
+            writeln!(f, "  warning: {}: no such language", name)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_language(extensions: &[&str]) -> RawLanguage {
+        serde_json::from_value(serde_json::json!({
+            "type": "programming",
+            "ace_mode": "text",
+            "aliases": ["existing"],
+            "extensions": extensions,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn apply_reports_changes_warnings_and_unmatched_overrides() {
+        let mut languages = BTreeMap::from([
+            ("Dropped".to_string(), raw_language(&[".d"])),
+            ("Changed".to_string(), raw_language(&[".a", ".b"])),
+        ]);
+        let overrides = Overrides(BTreeMap::from([
+            (
+                "Dropped".to_string(),
+                Override {
+                    drop: true,
+                    ..Override::default()
+                },
+            ),
+            (
+                "Changed".to_string(),
+                Override {
+                    remove_extensions: vec![".b".to_string(), ".gone".to_string()],
+                    ace_mode: Some("text".to_string()),
+                    color: Some("#123456".to_string()),
+                    add_aliases: vec!["existing".to_string(), "new".to_string()],
+                    ..Override::default()
+                },
+            ),
+            (
+                "Missing".to_string(),
+                Override {
+                    drop: true,
+                    ..Override::default()
+                },
+            ),
+        ]));
+
+        let report = overrides.apply(&mut languages);
+
+        assert!(!languages.contains_key("Dropped"));
+        let changed = &languages["Changed"];
+        assert_eq!(changed.extensions, [".a"]);
+        assert_eq!(changed.color, "#123456");
+        assert_eq!(changed.aliases, ["existing", "new"]);
+
+        assert_eq!(report.unmatched, ["Missing"]);
+        assert_eq!(report.applied.len(), 2);
+
+        let applied = &report.applied[0];
+        assert_eq!(applied.language, "Changed");
+        assert_eq!(
+            applied.changes,
+            [
+                "removed extension .b",
+                "set color from #FFFFFF to #123456",
+                "added alias new",
+            ],
+        );
+        assert_eq!(
+            applied.warnings,
+            [
+                "extension .gone is not present",
+                "ace_mode is already text",
+                "alias existing is already present",
+            ],
+        );
+
+        assert_eq!(report.applied[1].language, "Dropped");
+        assert_eq!(report.applied[1].changes, ["dropped"]);
+    }
+}
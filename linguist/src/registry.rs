@@ -0,0 +1,440 @@
+use crate::{Language, RawLanguage};
+
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    io::Write,
+    str::FromStr,
+};
+
+/// An editor whose mode field decides which languages are only highlighted as plain text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditorMode {
+    /// Languages with an `ace_mode` of `text` are plain text.
+    #[default]
+    Ace,
+    /// Languages without a `codemirror_mode` are plain text.
+    CodeMirror,
+}
+
+impl EditorMode {
+    /// Whether the editor can only highlight the given language as plain text.
+    pub fn is_text_only(self, language: &RawLanguage) -> bool {
+        match self {
+            Self::Ace => language.ace_mode == "text",
+            Self::CodeMirror => language.codemirror_mode.is_none(),
+        }
+    }
+}
+
+impl FromStr for EditorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ace" => Ok(Self::Ace),
+            "codemirror" => Ok(Self::CodeMirror),
+            _ => Err(format!("unknown editor mode {:?}", s)),
+        }
+    }
+}
+
+/// A set of languages that can be looked up by name, alias, extension, filename, interpreter or
+/// Ace mode.
+///
+/// Lookups by name, alias and extension are case-insensitive, while filenames must match exactly.
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    languages: BTreeMap<String, Language>,
+    // Lowercase name -> canonical name
+    names: HashMap<String, String>,
+    // Lowercase alias -> canonical name
+    aliases: HashMap<String, String>,
+    // Exact filename -> canonical name
+    filenames: HashMap<String, String>,
+    // Exact interpreter -> canonical name
+    interpreters: HashMap<String, String>,
+    // Lowercase extension including the leading dot -> (rank, canonical name)
+    extensions: HashMap<String, (u8, String)>,
+    // Lowercase extension including the leading dot -> canonical names of every language claiming
+    // it, in alphabetical order
+    extension_claims: HashMap<String, Vec<String>>,
+}
+
+/// Ranks a language claiming an extension, where lower ranks take priority. Languages named after
+/// the extension come first, then languages with it as their primary (first) extension. Any
+/// remaining ties go to the language that comes first alphabetically.
+fn extension_rank(language: &Language, extension: &str, idx: usize) -> u8 {
+    let stem = extension.trim_start_matches('.');
+
+    if language.name.eq_ignore_ascii_case(stem)
+        || language
+            .aliases
+            .iter()
+            .any(|alias| alias.eq_ignore_ascii_case(stem))
+    {
+        0
+    } else if idx == 0 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Parses GitHub Linguist's `languages.yml` into its languages, keyed by name.
+#[cfg(feature = "yaml")]
+pub fn parse_languages_yml(yaml: &[u8]) -> serde_yaml::Result<BTreeMap<String, RawLanguage>> {
+    serde_yaml::from_slice(yaml)
+}
+
+fn normalize_extension(extension: &str) -> String {
+    let extension = extension.to_lowercase();
+
+    if extension.starts_with('.') {
+        extension
+    } else {
+        format!(".{}", extension)
+    }
+}
+
+impl Registry {
+    pub fn new(languages: impl IntoIterator<Item = Language>) -> Self {
+        let mut registry = Self {
+            languages: languages
+                .into_iter()
+                .map(|language| (language.name.clone(), language))
+                .collect(),
+            ..Self::default()
+        };
+
+        // Languages are visited in alphabetical order, which makes every tie deterministic
+        for language in registry.languages.values() {
+            registry
+                .names
+                .insert(language.name.to_lowercase(), language.name.clone());
+
+            for alias in &language.aliases {
+                registry
+                    .aliases
+                    .entry(alias.to_lowercase())
+                    .or_insert_with(|| language.name.clone());
+            }
+
+            for filename in &language.filenames {
+                registry
+                    .filenames
+                    .entry(filename.clone())
+                    .or_insert_with(|| language.name.clone());
+            }
+
+            for interpreter in &language.interpreters {
+                registry
+                    .interpreters
+                    .entry(interpreter.clone())
+                    .or_insert_with(|| language.name.clone());
+            }
+
+            for (idx, extension) in language.extensions.iter().enumerate() {
+                let extension = normalize_extension(extension);
+                let rank = extension_rank(language, &extension, idx);
+
+                registry
+                    .extension_claims
+                    .entry(extension.clone())
+                    .or_default()
+                    .push(language.name.clone());
+
+                match registry.extensions.entry(extension) {
+                    Entry::Occupied(mut entry) if rank < entry.get().0 => {
+                        entry.insert((rank, language.name.clone()));
+                    }
+                    Entry::Occupied(_) => (),
+                    Entry::Vacant(entry) => {
+                        entry.insert((rank, language.name.clone()));
+                    }
+                }
+            }
+        }
+
+        registry
+    }
+
+    /// Parses a generated `languages.json`, which maps language names to [`Language`]s.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        Ok(Self::new(
+            serde_json::from_str::<HashMap<String, Language>>(json)?.into_values(),
+        ))
+    }
+
+    /// Builds a registry from the languages of GitHub Linguist's `languages.yml`, dropping
+    /// languages that the given editor only highlights as plain text. Every language is kept if
+    /// no editor is given.
+    pub fn from_raw(
+        languages: impl IntoIterator<Item = (String, RawLanguage)>,
+        editor_mode: Option<EditorMode>,
+    ) -> Self {
+        Self::new(languages.into_iter().filter_map(|(name, raw)| {
+            // Remove anything that can give a false assumption to the user that
+            // the language is supported, even though it really is just text
+            if editor_mode.is_some_and(|mode| mode.is_text_only(&raw)) && name != "Text" {
+                return None;
+            }
+
+            Some(Language::from_raw(name, raw))
+        }))
+    }
+
+    /// Parses GitHub Linguist's `languages.yml`, dropping languages that Ace only highlights as
+    /// plain text. Use [`parse_languages_yml`] and [`Registry::from_raw`] to apply
+    /// [`Overrides`](crate::Overrides) in between.
+    #[cfg(feature = "yaml")]
+    pub fn from_languages_yml(yaml: &[u8]) -> serde_yaml::Result<Self> {
+        Ok(Self::from_raw(
+            parse_languages_yml(yaml)?,
+            Some(EditorMode::Ace),
+        ))
+    }
+
+    /// Writes the registry in the format of `languages.json`. Languages are sorted by name, so the
+    /// output only changes when the languages do.
+    pub fn to_json_writer(&self, writer: impl Write) -> serde_json::Result<()> {
+        serde_json::to_writer(writer, &self.languages)
+    }
+
+    /// Like [`Registry::to_json_writer`], but pretty-printed.
+    pub fn to_json_writer_pretty(&self, writer: impl Write) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(writer, &self.languages)
+    }
+
+    /// Maps every known extension, in lowercase and including the leading dot, to the canonical
+    /// name of the language that owns it. See [`Registry::find_by_extension`].
+    pub fn extension_index(&self) -> BTreeMap<&str, &str> {
+        self.extensions
+            .iter()
+            .map(|(extension, (_, name))| (extension.as_str(), name.as_str()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.languages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty()
+    }
+
+    /// Iterates over every language in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = &Language> {
+        self.languages.values()
+    }
+
+    /// Looks up a language by its exact canonical name.
+    pub fn get(&self, name: &str) -> Option<&Language> {
+        self.languages.get(name)
+    }
+
+    fn resolve(&self, name: Option<&String>) -> Option<&Language> {
+        name.and_then(|name| self.languages.get(name))
+    }
+
+    /// Looks up a language by its name or one of its aliases. Names take priority over aliases.
+    pub fn find(&self, name: &str) -> Option<&Language> {
+        let name = name.to_lowercase();
+
+        self.resolve(self.names.get(&name))
+            .or_else(|| self.resolve(self.aliases.get(&name)))
+    }
+
+    /// Looks up a language by one of its aliases.
+    pub fn find_by_alias(&self, alias: &str) -> Option<&Language> {
+        self.resolve(self.aliases.get(&alias.to_lowercase()))
+    }
+
+    /// Looks up the language that owns an extension, with or without its leading dot. When
+    /// multiple languages share the extension, the one it ranks highest for is returned.
+    pub fn find_by_extension(&self, extension: &str) -> Option<&Language> {
+        self.resolve(
+            self.extensions
+                .get(&normalize_extension(extension))
+                .map(|(_, name)| name),
+        )
+    }
+
+    /// Iterates over every language claiming an extension, with or without its leading dot, in
+    /// alphabetical order.
+    pub fn find_all_by_extension<'a>(
+        &'a self,
+        extension: &str,
+    ) -> impl Iterator<Item = &'a Language> + 'a {
+        self.extension_claims
+            .get(&normalize_extension(extension))
+            .into_iter()
+            .flatten()
+            .filter_map(|name| self.languages.get(name))
+    }
+
+    /// Looks up a language by an exact filename, such as `Makefile`.
+    pub fn find_by_filename(&self, filename: &str) -> Option<&Language> {
+        self.resolve(self.filenames.get(filename))
+    }
+
+    /// Looks up a language by the exact name of an interpreter that runs it, such as `python3`.
+    pub fn find_by_interpreter(&self, interpreter: &str) -> Option<&Language> {
+        self.resolve(self.interpreters.get(interpreter))
+    }
+
+    /// Iterates over the languages grouped under the language with the given canonical name, in
+    /// alphabetical order. For example, JSX is grouped under JavaScript.
+    pub fn group_members<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Language> {
+        self.iter()
+            .filter(move |language| language.group.as_deref() == Some(name))
+    }
+
+    /// Iterates over the languages highlighted with the given Ace mode, in alphabetical order.
+    pub fn find_by_ace_mode<'a>(&'a self, ace_mode: &'a str) -> impl Iterator<Item = &'a Language> {
+        self.iter()
+            .filter(move |language| language.ace_mode == ace_mode)
+    }
+
+    /// Infers the language of a file from its path, first by exact filename (such as
+    /// `Dockerfile`) and then by its longest known extension (such as `.yml.mysql` over `.mysql`).
+    pub fn detect(&self, path: &str) -> Option<&Language> {
+        let filename = path.rsplit(['/', '\\']).next().unwrap_or(path);
+
+        self.find_by_filename(filename).or_else(|| {
+            self.longest_extension(filename)
+                .and_then(|extension| self.find_by_extension(&extension))
+        })
+    }
+
+    /// The longest extension of a path that belongs to any language, in lowercase and including the
+    /// leading dot.
+    pub fn longest_extension(&self, path: &str) -> Option<String> {
+        let filename = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        let lowercase = filename.to_lowercase();
+
+        lowercase
+            .match_indices('.')
+            .map(|(i, _)| &lowercase[i..])
+            .find(|extension| self.extensions.contains_key(*extension))
+            .map(ToString::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::test_language;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn find_matches_names_and_aliases_case_insensitively() {
+        let registry = Registry::new([
+            Language {
+                aliases: strings(&["py", "python3"]),
+                ..test_language("Python")
+            },
+            Language {
+                aliases: strings(&["c++"]),
+                ..test_language("CPP")
+            },
+        ]);
+
+        assert_eq!(registry.find("python").unwrap().name, "Python");
+        assert_eq!(registry.find("PYTHON").unwrap().name, "Python");
+        assert_eq!(registry.find("Py").unwrap().name, "Python");
+        assert_eq!(registry.find("C++").unwrap().name, "CPP");
+        assert!(registry.find("ruby").is_none());
+        assert!(registry.get("python").is_none());
+    }
+
+    #[test]
+    fn find_prefers_names_over_aliases() {
+        let registry = Registry::new([
+            Language {
+                aliases: strings(&["text"]),
+                ..test_language("Markdown")
+            },
+            test_language("Text"),
+        ]);
+
+        assert_eq!(registry.find("text").unwrap().name, "Text");
+        assert_eq!(registry.find_by_alias("text").unwrap().name, "Markdown");
+    }
+
+    #[test]
+    fn extensions_rank_named_then_primary_then_alphabetical() {
+        let registry = Registry::new([
+            Language {
+                extensions: strings(&[".zed", ".shared"]),
+                ..test_language("Alpha")
+            },
+            Language {
+                extensions: strings(&[".shared", ".zed"]),
+                ..test_language("Beta")
+            },
+            Language {
+                extensions: strings(&[".other", ".zed"]),
+                aliases: strings(&["zed"]),
+                ..test_language("Gamma")
+            },
+            Language {
+                extensions: strings(&[".zed", ".shared"]),
+                ..test_language("Delta")
+            },
+        ]);
+
+        // Gamma is named after the extension, even though it isn't its primary extension
+        assert_eq!(registry.find_by_extension(".zed").unwrap().name, "Gamma");
+        // Beta is the only language with it as its primary extension
+        assert_eq!(registry.find_by_extension("SHARED").unwrap().name, "Beta");
+
+        let claims = registry
+            .find_all_by_extension("shared")
+            .map(|language| language.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(claims, ["Alpha", "Beta", "Delta"]);
+
+        let registry = Registry::new([
+            Language {
+                extensions: strings(&[".tie"]),
+                ..test_language("Zulu")
+            },
+            Language {
+                extensions: strings(&[".tie"]),
+                ..test_language("Yankee")
+            },
+        ]);
+        assert_eq!(registry.find_by_extension(".tie").unwrap().name, "Yankee");
+    }
+
+    #[test]
+    fn longest_extension_prefers_compound_extensions() {
+        let registry = Registry::new([
+            Language {
+                extensions: strings(&[".yml", ".yaml"]),
+                ..test_language("YAML")
+            },
+            Language {
+                extensions: strings(&[".yml.mysql"]),
+                ..test_language("SQL")
+            },
+        ]);
+
+        assert_eq!(
+            registry.longest_extension("db/schema.yml.mysql").as_deref(),
+            Some(".yml.mysql"),
+        );
+        assert_eq!(
+            registry.longest_extension("Config.Local.YML").as_deref(),
+            Some(".yml"),
+        );
+        assert_eq!(registry.longest_extension("archive.tar"), None);
+        assert_eq!(registry.longest_extension("dir.yml/README"), None);
+
+        assert_eq!(registry.detect("db/schema.yml.mysql").unwrap().name, "SQL");
+        assert_eq!(registry.detect("docker-compose.yml").unwrap().name, "YAML");
+    }
+}
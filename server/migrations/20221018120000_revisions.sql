@@ -0,0 +1,33 @@
+CREATE TABLE IF NOT EXISTS paste_revisions (
+    paste_id TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (paste_id, revision),
+    CONSTRAINT paste_fk
+        FOREIGN KEY (paste_id)
+        REFERENCES pastes(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS file_revisions (
+    paste_id TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    idx SMALLINT NOT NULL,
+    filename TEXT,
+    content TEXT NOT NULL,
+    language TEXT,
+    PRIMARY KEY (paste_id, revision, idx),
+    CONSTRAINT revision_fk
+        FOREIGN KEY (paste_id, revision)
+        REFERENCES paste_revisions(paste_id, revision)
+        ON DELETE CASCADE
+);
+
+-- Existing pastes start out at revision 0
+INSERT INTO paste_revisions
+SELECT id, 0, name, description, created_at FROM pastes;
+
+INSERT INTO file_revisions
+SELECT paste_id, 0, idx, filename, content, language FROM files;
@@ -0,0 +1,3 @@
+ALTER TABLE pastes ADD COLUMN expires_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS pastes_expires_at_idx ON pastes (expires_at) WHERE expires_at IS NOT NULL;
@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS drafts (
+    id TEXT NOT NULL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT,
+    description TEXT,
+    -- Same as pastes.visibility
+    visibility SMALLINT NOT NULL DEFAULT 2,
+    -- Seconds after publishing until the paste expires
+    expires_in BIGINT,
+    burn_after_read BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT user_fk
+        FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS drafts_user_id_idx ON drafts (user_id);
+
+CREATE TABLE IF NOT EXISTS draft_files (
+    draft_id TEXT NOT NULL,
+    idx SMALLINT NOT NULL,
+    filename TEXT,
+    content TEXT NOT NULL,
+    language TEXT,
+    PRIMARY KEY (draft_id, idx),
+    CONSTRAINT draft_fk
+        FOREIGN KEY (draft_id)
+        REFERENCES drafts(id)
+        ON DELETE CASCADE
+);
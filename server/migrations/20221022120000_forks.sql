@@ -0,0 +1,3 @@
+ALTER TABLE pastes ADD COLUMN forked_from TEXT REFERENCES pastes(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS pastes_forked_from_idx ON pastes (forked_from) WHERE forked_from IS NOT NULL;
@@ -0,0 +1,17 @@
+-- Only the start of large files is searchable, since a tsvector is limited to 1 MB
+CREATE OR REPLACE FUNCTION paste_search_vector(name TEXT, description TEXT) RETURNS tsvector
+    LANGUAGE SQL IMMUTABLE PARALLEL SAFE
+    AS $$
+        SELECT setweight(to_tsvector('simple', name), 'A')
+            || setweight(to_tsvector('simple', COALESCE(description, '')), 'B')
+    $$;
+
+CREATE OR REPLACE FUNCTION file_search_vector(filename TEXT, content TEXT) RETURNS tsvector
+    LANGUAGE SQL IMMUTABLE PARALLEL SAFE
+    AS $$
+        SELECT setweight(to_tsvector('simple', COALESCE(filename, '')), 'A')
+            || setweight(to_tsvector('simple', LEFT(content, 100000)), 'C')
+    $$;
+
+CREATE INDEX IF NOT EXISTS pastes_search_idx ON pastes USING GIN (paste_search_vector(name, description));
+CREATE INDEX IF NOT EXISTS files_search_idx ON files USING GIN (file_search_vector(filename, content));
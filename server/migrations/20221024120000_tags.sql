@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS paste_tags (
+    paste_id TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (paste_id, tag),
+    CONSTRAINT paste_fk
+        FOREIGN KEY (paste_id)
+        REFERENCES pastes(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS paste_tags_tag_idx ON paste_tags (tag);
+
+ALTER TABLE drafts ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
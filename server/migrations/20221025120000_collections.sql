@@ -0,0 +1,38 @@
+CREATE TABLE IF NOT EXISTS collections (
+    id TEXT NOT NULL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    -- Same as pastes.visibility
+    visibility SMALLINT NOT NULL,
+    -- Will be hashed
+    password TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT user_fk
+        FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS collections_user_id_idx ON collections (user_id);
+
+CREATE TABLE IF NOT EXISTS collection_pastes (
+    collection_id TEXT NOT NULL,
+    paste_id TEXT NOT NULL,
+    -- Pastes are ordered by this. Removing a paste from the collection closes its gap, but
+    -- deleting the paste itself may leave one behind, so positions aren't always contiguous.
+    idx SMALLINT NOT NULL,
+    added_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (collection_id, paste_id),
+    CONSTRAINT collection_fk
+        FOREIGN KEY (collection_id)
+        REFERENCES collections(id)
+        ON DELETE CASCADE,
+    CONSTRAINT paste_fk
+        FOREIGN KEY (paste_id)
+        REFERENCES pastes(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS collection_pastes_paste_id_idx ON collection_pastes (paste_id);
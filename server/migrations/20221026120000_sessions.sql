@@ -0,0 +1,26 @@
+-- Every token is a session. Its ID is public, so that sessions can be listed and revoked without
+-- exposing the token itself.
+ALTER TABLE tokens
+    ADD COLUMN id TEXT,
+    ADD COLUMN last_used_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip TEXT;
+
+-- Existing tokens get IDs like `generate_id::<12>()` makes: 12 random bytes as unpadded URL-safe
+-- base64. The first 6 bytes of a v4 UUID are all random, so two of them make up the 12 bytes.
+UPDATE tokens
+SET id = TRANSLATE(
+    ENCODE(
+        SUBSTRING(uuid_send(gen_random_uuid()) FROM 1 FOR 6)
+            || SUBSTRING(uuid_send(gen_random_uuid()) FROM 1 FOR 6),
+        'base64'
+    ),
+    '+/',
+    '-_'
+)
+WHERE id IS NULL;
+
+ALTER TABLE tokens ALTER COLUMN id SET NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS tokens_id_idx ON tokens (id);
+CREATE INDEX IF NOT EXISTS tokens_user_id_idx ON tokens (user_id);
@@ -0,0 +1,127 @@
+use crate::routes::{pastes::File, raw::default_filename};
+
+use flate2::{write::GzEncoder, Compression};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    #[default]
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::TarGz => "tar.gz",
+        }
+    }
+
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Zip => "application/zip",
+            Self::TarGz => "application/gzip",
+        }
+    }
+}
+
+/// Turns a filename into one that is safe to use as an archive entry, so that extracting the
+/// archive can never write outside of the directory it is extracted to.
+fn sanitize_filename(filename: &str) -> Option<String> {
+    let filename = filename
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+
+    match filename.trim() {
+        "" | "." | ".." => None,
+        _ => Some(filename),
+    }
+}
+
+/// Picks a unique entry name for each file. Unnamed files fall back to `file<n>.txt`, and
+/// clashing names (ignoring case, since not every filesystem is case sensitive) are numbered
+/// like `main (2).rs` in the order the files appear in the paste.
+pub fn entry_names(files: &[File]) -> Vec<String> {
+    let mut taken = HashSet::with_capacity(files.len());
+
+    files
+        .iter()
+        .enumerate()
+        .map(|(idx, file)| {
+            let name = file
+                .filename
+                .as_deref()
+                .and_then(sanitize_filename)
+                .unwrap_or_else(|| default_filename(idx));
+
+            if taken.insert(name.to_lowercase()) {
+                return name;
+            }
+
+            // Dotfiles such as ".env" have no extension
+            let (stem, extension) = match name.rfind('.') {
+                Some(i) if i > 0 => name.split_at(i),
+                _ => (name.as_str(), ""),
+            };
+
+            (2..)
+                .map(|n| format!("{} ({}){}", stem, n, extension))
+                .find(|candidate| taken.insert(candidate.to_lowercase()))
+                .expect("there are finitely many files")
+        })
+        .collect()
+}
+
+fn build_zip(files: &[File], names: &[String]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+    // The default modification time is fixed, which keeps archives deterministic
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .unix_permissions(0o644);
+
+    for (file, name) in files.iter().zip(names) {
+        writer.start_file(name, options)?;
+        writer.write_all(file.content.as_bytes())?;
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+fn build_tar_gz(files: &[File], names: &[String]) -> io::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    for (file, name) in files.iter().zip(names) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(file.content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_cksum();
+
+        builder.append_data(&mut header, name, file.content.as_bytes())?;
+    }
+
+    builder.into_inner()?.finish()
+}
+
+/// Packages the files of a paste into an archive of the given format.
+pub fn build_archive(files: &[File], format: ArchiveFormat) -> Result<Vec<u8>, String> {
+    let names = entry_names(files);
+
+    match format {
+        ArchiveFormat::Zip => build_zip(files, &names).map_err(|e| e.to_string()),
+        ArchiveFormat::TarGz => build_tar_gz(files, &names).map_err(|e| e.to_string()),
+    }
+}
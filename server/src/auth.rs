@@ -1,5 +1,5 @@
 use argon2_async::{set_config, Config};
-use base64::{encode_config, URL_SAFE_NO_PAD};
+use base64::{decode_config, encode_config, URL_SAFE_NO_PAD};
 use ring::rand::{SecureRandom, SystemRandom};
 
 use std::{
@@ -66,3 +66,14 @@ pub fn generate_token(mut user_id: String) -> String {
 
     user_id
 }
+
+/// The Unix timestamp in milliseconds of when a token was generated, decoded from its epoch part.
+pub fn token_created_at(token: &str) -> Option<u128> {
+    let epoch = decode_config(token.split('.').nth(1)?, URL_SAFE_NO_PAD).ok()?;
+
+    String::from_utf8(epoch)
+        .ok()?
+        .parse::<u128>()
+        .ok()
+        .map(|epoch| epoch + TOKEN_EPOCH)
+}
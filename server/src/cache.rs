@@ -1,3 +1,4 @@
+use chrono::Utc;
 use deadpool_redis::{Config, Pool, Runtime};
 use redis::AsyncCommands;
 use std::sync::OnceLock;
@@ -21,38 +22,104 @@ pub async fn setup() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Maps tokens to the ID of the user they belong to.
+const TOKEN_TO_ID: &str = "turbine_token_to_id";
+/// Maps tokens to the Unix timestamp of when they were last used. `tokens.last_used_at` is only
+/// updated when a token isn't cached, so that not every authorized request writes to the database.
+const TOKEN_LAST_USED: &str = "turbine_token_last_used";
+
 pub async fn resolve_token(token: &str) -> Result<String, JsonResponse<Error>> {
-    if let Some(id) = POOL
+    let mut conn = POOL
         .get()
         .expect("Didn't call `cache::setup`")
         .get()
-        .await?
-        .hget::<_, _, Option<String>>("turbine_token_to_id", token)
+        .await?;
+
+    if let Some(id) = conn
+        .hget::<_, _, Option<String>>(TOKEN_TO_ID, token)
         .await?
     {
+        conn.hset::<_, _, _, ()>(TOKEN_LAST_USED, token, Utc::now().timestamp())
+            .await?;
+
         return Ok(id);
     }
 
-    let id = sqlx::query!("SELECT user_id FROM tokens WHERE token = $1", token)
-        .fetch_optional(get_pool())
-        .await?
-        .ok_or_else(|| {
-            (
-                404,
-                Error {
-                    message: "Invalid authorization token".to_string(),
-                },
-            )
-        })?
-        .user_id;
-
-    let _ = POOL
-        .get()
-        .expect("Didn't call `cache::setup`")
-        .get()
-        .await?
-        .hset::<_, _, _, ()>("turbine_token_to_id", token, &id)
-        .await?;
+    let invalid = || {
+        JsonResponse::from((
+            404,
+            Error {
+                message: "Invalid authorization token".to_string(),
+            },
+        ))
+    };
+
+    let id = sqlx::query!(
+        "UPDATE tokens SET last_used_at = CURRENT_TIMESTAMP WHERE token = $1 RETURNING user_id",
+        token,
+    )
+    .fetch_optional(get_pool())
+    .await?
+    .ok_or_else(invalid)?
+    .user_id;
+
+    let _ = conn.hset::<_, _, _, ()>(TOKEN_TO_ID, token, &id).await?;
+
+    // Revoking a token deletes it and then evicts it. If that happened since it was read above,
+    // the eviction may have run before it was cached, so make sure it still exists now that it is.
+    if !sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM tokens WHERE token = $1) AS "exists!""#,
+        token,
+    )
+    .fetch_one(get_pool())
+    .await?
+    .exists
+    {
+        evict_tokens(&[token.to_string()]).await?;
+        return Err(invalid());
+    }
 
     Ok(id)
 }
+
+/// The Unix timestamps of when the given tokens were last used, if they were used since they were
+/// cached.
+pub async fn tokens_last_used(tokens: &[String]) -> Result<Vec<Option<i64>>, JsonResponse<Error>> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(redis::cmd("HMGET")
+        .arg(TOKEN_LAST_USED)
+        .arg(tokens)
+        .query_async(
+            &mut POOL
+                .get()
+                .expect("Didn't call `cache::setup`")
+                .get()
+                .await?,
+        )
+        .await?)
+}
+
+/// Removes the given tokens from the cache. This must be called after deleting tokens, otherwise
+/// they would keep resolving to their user.
+pub async fn evict_tokens(tokens: &[String]) -> Result<(), JsonResponse<Error>> {
+    if tokens.is_empty() {
+        return Ok(());
+    }
+
+    redis::pipe()
+        .hdel(TOKEN_TO_ID, tokens)
+        .hdel(TOKEN_LAST_USED, tokens)
+        .query_async::<_, ()>(
+            &mut POOL
+                .get()
+                .expect("Didn't call `cache::setup`")
+                .get()
+                .await?,
+        )
+        .await?;
+
+    Ok(())
+}
@@ -5,7 +5,7 @@ use sqlx::{
     Pool, Postgres,
 };
 
-use std::{error::Error, sync::OnceLock};
+use std::{error::Error, sync::OnceLock, time::Duration};
 
 pub static POOL: OnceLock<Pool<Postgres>> = OnceLock::new();
 
@@ -47,3 +47,23 @@ pub async fn migrate() {
         .await
         .expect("failed to run database migrations");
 }
+
+/// Spawns a background task which periodically deletes pastes that have expired.
+pub fn spawn_reaper() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = sqlx::query!(
+                "DELETE FROM pastes WHERE expires_at IS NOT NULL AND expires_at <= CURRENT_TIMESTAMP"
+            )
+            .execute(get_pool())
+            .await
+            {
+                eprintln!("Failed to delete expired pastes: {:?}", err);
+            }
+        }
+    });
+}
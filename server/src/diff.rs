@@ -0,0 +1,338 @@
+use crate::routes::pastes::File;
+
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Added,
+    Removed,
+    Modified,
+    Renamed,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    // Line numbers are 1-indexed
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    pub content: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DiffHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileDiff {
+    pub status: FileStatus,
+    pub old_filename: Option<String>,
+    pub new_filename: Option<String>,
+    pub old_idx: Option<u16>,
+    pub new_idx: Option<u16>,
+    pub additions: u32,
+    pub deletions: u32,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// Pairs up the files of two versions of a paste.
+///
+/// Files are first matched by filename (unnamed files are matched by index). Remaining files with
+/// identical content, and after that remaining files at the same index, are treated as renames.
+/// Anything left over was either added or removed.
+fn pair_files(old: &[File], new: &[File]) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut pairs = Vec::with_capacity(old.len().max(new.len()));
+    let mut old_matched = vec![false; old.len()];
+    let mut new_matched = vec![false; new.len()];
+
+    let mut pair_by = |matches: &dyn Fn(usize, usize) -> bool| {
+        for (j, new_matched) in new_matched.iter_mut().enumerate() {
+            if *new_matched {
+                continue;
+            }
+
+            if let Some(i) = (0..old.len()).find(|&i| !old_matched[i] && matches(i, j)) {
+                old_matched[i] = true;
+                *new_matched = true;
+                pairs.push((Some(i), Some(j)));
+            }
+        }
+    };
+
+    pair_by(&|i, j| match (&old[i].filename, &new[j].filename) {
+        (Some(a), Some(b)) => a == b,
+        (None, None) => i == j,
+        _ => false,
+    });
+    pair_by(&|i, j| old[i].content == new[j].content);
+    pair_by(&|i, j| i == j);
+
+    pairs.extend(
+        new_matched
+            .iter()
+            .enumerate()
+            .filter(|(_, matched)| !**matched)
+            .map(|(j, _)| (None, Some(j))),
+    );
+    pairs.extend(
+        old_matched
+            .iter()
+            .enumerate()
+            .filter(|(_, matched)| !**matched)
+            .map(|(i, _)| (Some(i), None)),
+    );
+
+    // Order by position in the new version, with removed files last
+    pairs.sort_by_key(|&(i, j)| (j.unwrap_or(usize::MAX), i));
+    pairs
+}
+
+/// Converts a 0-indexed line range into the 1-indexed start line of a hunk. Like unified diffs,
+/// an empty range points at the line *before* the hunk, which is 0 if the hunk is at the start.
+fn hunk_start(range: &Range<usize>) -> u32 {
+    if range.is_empty() {
+        range.start as u32
+    } else {
+        range.start as u32 + 1
+    }
+}
+
+fn diff_file(old: Option<&File>, new: Option<&File>, context: usize) -> FileDiff {
+    let old_content = old.map(|file| file.content.as_str()).unwrap_or_default();
+    let new_content = new.map(|file| file.content.as_str()).unwrap_or_default();
+    let diff = TextDiff::from_lines(old_content, new_content);
+
+    let mut additions = 0;
+    let mut deletions = 0;
+    let hunks = diff
+        .grouped_ops(context)
+        .into_iter()
+        .map(|group| {
+            let (first, last) = (&group[0], &group[group.len() - 1]);
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| {
+                    let kind = match change.tag() {
+                        ChangeTag::Equal => LineKind::Context,
+                        ChangeTag::Insert => {
+                            additions += 1;
+                            LineKind::Added
+                        }
+                        ChangeTag::Delete => {
+                            deletions += 1;
+                            LineKind::Removed
+                        }
+                    };
+
+                    DiffLine {
+                        kind,
+                        old_line: change.old_index().map(|i| i as u32 + 1),
+                        new_line: change.new_index().map(|i| i as u32 + 1),
+                        content: change.value().trim_end_matches(['\n', '\r']).to_string(),
+                    }
+                })
+                .collect();
+
+            DiffHunk {
+                old_start: hunk_start(&old_range),
+                old_lines: old_range.len() as u32,
+                new_start: hunk_start(&new_range),
+                new_lines: new_range.len() as u32,
+                lines,
+            }
+        })
+        .collect();
+
+    let old_filename = old.and_then(|file| file.filename.clone());
+    let new_filename = new.and_then(|file| file.filename.clone());
+
+    FileDiff {
+        status: match (old, new) {
+            (None, _) => FileStatus::Added,
+            (_, None) => FileStatus::Removed,
+            _ if old_filename != new_filename => FileStatus::Renamed,
+            _ => FileStatus::Modified,
+        },
+        old_filename,
+        new_filename,
+        old_idx: None,
+        new_idx: None,
+        additions,
+        deletions,
+        hunks,
+    }
+}
+
+/// Computes a line-level diff between two versions of a paste's files, showing `context` lines of
+/// unchanged content around each hunk. Files that are unchanged are omitted.
+pub fn diff_files(old: &[File], new: &[File], context: usize) -> Vec<FileDiff> {
+    pair_files(old, new)
+        .into_iter()
+        .filter_map(|(i, j)| {
+            let (old_file, new_file) = (i.map(|i| &old[i]), j.map(|j| &new[j]));
+            let diff = FileDiff {
+                old_idx: i.map(|i| i as u16),
+                new_idx: j.map(|j| j as u16),
+                ..diff_file(old_file, new_file, context)
+            };
+
+            (diff.status != FileStatus::Modified || !diff.hunks.is_empty()).then_some(diff)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(filename: Option<&str>, content: &str) -> File {
+        File {
+            filename: filename.map(ToString::to_string),
+            content: content.to_string(),
+            language: None,
+        }
+    }
+
+    #[test]
+    fn hunk_start_points_before_empty_ranges() {
+        assert_eq!(hunk_start(&(0..0)), 0);
+        assert_eq!(hunk_start(&(3..3)), 3);
+        assert_eq!(hunk_start(&(0..2)), 1);
+        assert_eq!(hunk_start(&(3..5)), 4);
+    }
+
+    #[test]
+    fn pair_files_matches_by_filename_then_content_then_index() {
+        let old = [
+            file(Some("a.rs"), "a"),
+            file(Some("b.rs"), "b"),
+            file(Some("c.rs"), "c"),
+            file(Some("d.rs"), "d"),
+        ];
+        let new = [
+            file(Some("renamed.rs"), "c"),
+            file(Some("a.rs"), "changed"),
+            file(Some("other.rs"), "other"),
+        ];
+
+        assert_eq!(
+            pair_files(&old, &new),
+            [
+                (Some(2), Some(0)),
+                (Some(0), Some(1)),
+                (None, Some(2)),
+                (Some(1), None),
+                (Some(3), None),
+            ],
+        );
+    }
+
+    #[test]
+    fn pair_files_matches_unnamed_files_by_index() {
+        let old = [file(None, "a"), file(None, "b")];
+        let new = [file(None, "b"), file(None, "a"), file(None, "c")];
+
+        assert_eq!(
+            pair_files(&old, &new),
+            [(Some(0), Some(0)), (Some(1), Some(1)), (None, Some(2))],
+        );
+    }
+
+    #[test]
+    fn diff_file_reports_added_and_removed_files() {
+        let contents = file(Some("a.rs"), "one\ntwo\n");
+
+        let added = diff_file(None, Some(&contents), 3);
+        assert_eq!(added.status, FileStatus::Added);
+        assert_eq!((added.additions, added.deletions), (2, 0));
+        let hunk = &added.hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (0, 0));
+        assert_eq!((hunk.new_start, hunk.new_lines), (1, 2));
+
+        let removed = diff_file(Some(&contents), None, 3);
+        assert_eq!(removed.status, FileStatus::Removed);
+        assert_eq!((removed.additions, removed.deletions), (0, 2));
+        let hunk = &removed.hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (1, 2));
+        assert_eq!((hunk.new_start, hunk.new_lines), (0, 0));
+    }
+
+    #[test]
+    fn diff_file_reports_renames_without_hunks() {
+        let diff = diff_file(
+            Some(&file(Some("old.rs"), "same\n")),
+            Some(&file(Some("new.rs"), "same\n")),
+            3,
+        );
+
+        assert_eq!(diff.status, FileStatus::Renamed);
+        assert_eq!(diff.old_filename.as_deref(), Some("old.rs"));
+        assert_eq!(diff.new_filename.as_deref(), Some("new.rs"));
+        assert!(diff.hunks.is_empty());
+    }
+
+    #[test]
+    fn diff_file_caps_context() {
+        let old = (1..=20).map(|i| format!("{i}\n")).collect::<String>();
+        let new = old.replace("10\n", "ten\n");
+        let diff = diff_file(
+            Some(&file(Some("a"), &old)),
+            Some(&file(Some("a"), &new)),
+            2,
+        );
+
+        assert_eq!(diff.status, FileStatus::Modified);
+        assert_eq!((diff.additions, diff.deletions), (1, 1));
+        assert_eq!(diff.hunks.len(), 1);
+
+        let hunk = &diff.hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (8, 5));
+        assert_eq!((hunk.new_start, hunk.new_lines), (8, 5));
+
+        let kinds = hunk.lines.iter().map(|line| line.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                LineKind::Context,
+                LineKind::Context,
+                LineKind::Removed,
+                LineKind::Added,
+                LineKind::Context,
+                LineKind::Context,
+            ],
+        );
+        assert_eq!(hunk.lines[2].content, "10");
+        assert_eq!(hunk.lines[2].old_line, Some(10));
+        assert_eq!(hunk.lines[3].new_line, Some(10));
+    }
+
+    #[test]
+    fn diff_files_omits_unchanged_files() {
+        let old = [file(Some("a"), "a\n"), file(Some("b"), "b\n")];
+        let new = [file(Some("a"), "a\n"), file(Some("b"), "c\n")];
+
+        let diffs = diff_files(&old, &new, 3);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!((diffs[0].old_idx, diffs[0].new_idx), (Some(1), Some(1)));
+    }
+}
@@ -107,3 +107,14 @@ impl From<reqwest::Error> for JsonResponse<Error> {
         )
     }
 }
+
+impl From<tokio::task::JoinError> for JsonResponse<Error> {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error {
+                message: format!("Background task failed: {:?}", err),
+            },
+        )
+    }
+}
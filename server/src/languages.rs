@@ -0,0 +1,51 @@
+use base64::{encode_config, URL_SAFE_NO_PAD};
+use linguist::{Detection, Registry};
+use ring::digest::{digest, SHA256};
+use std::sync::OnceLock;
+
+/// The language data generated from GitHub Linguist by the `linguist` tool. This is a copy of the
+/// frontend's `languages.json`, kept in this crate so that it builds on its own.
+pub const LANGUAGES_JSON: &str = include_str!("../languages.json");
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+static ETAG: OnceLock<String> = OnceLock::new();
+
+pub fn get_registry() -> &'static Registry {
+    REGISTRY.get_or_init(|| {
+        Registry::from_json(LANGUAGES_JSON).expect("could not parse languages.json")
+    })
+}
+
+/// A strong ETag of [`LANGUAGES_JSON`], which only changes when the language data does. This is
+/// a SHA-256 digest so that it stays the same across builds and toolchains.
+pub fn languages_etag() -> &'static str {
+    ETAG.get_or_init(|| {
+        let hash = digest(&SHA256, LANGUAGES_JSON.as_bytes());
+
+        format!("\"{}\"", encode_config(hash, URL_SAFE_NO_PAD))
+    })
+}
+
+/// Resolves a language name or alias (case-insensitive) into the canonical name of the language.
+pub fn resolve_language(name: &str) -> Option<&'static str> {
+    get_registry()
+        .find(name.trim())
+        .map(|language| language.name.as_str())
+}
+
+/// Only this many bytes at the start of a file are looked at when detecting its language.
+const MAX_DETECT_LEN: usize = 64 * 1024;
+
+/// Languages detected with less confidence than this are not inferred for paste files.
+pub const INFER_MIN_CONFIDENCE: f64 = 0.5;
+
+/// Detects the language of a file from its filename, if it has one, and its content. See
+/// [`Registry::detect_file`].
+pub fn detect_file(filename: Option<&str>, content: &str) -> Option<Detection<'static>> {
+    let mut end = content.len().min(MAX_DETECT_LEN);
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    get_registry().detect_file(filename, &content[..end])
+}
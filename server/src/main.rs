@@ -3,14 +3,19 @@
 #![feature(is_some_with)]
 #![feature(try_blocks)]
 
+pub mod archive;
 pub mod auth;
 pub mod cache;
 pub mod config;
 pub mod database;
+pub mod diff;
 pub mod json;
+pub mod languages;
 pub mod oauth;
+pub mod pagination;
 pub mod ratelimit;
 pub mod routes;
+pub mod upload;
 
 use axum::{http::StatusCode, routing::get, Router};
 use std::net::SocketAddr;
@@ -25,12 +30,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     cache::setup().await?;
     database::connect().await?;
     database::migrate().await;
+    database::spawn_reaper();
     auth::configure_hasher().await;
     oauth::setup();
 
     let router = Router::new()
         .route("/api", get(|| async { (StatusCode::OK, "Hello, world!") }))
+        .nest("/api", routes::collections::router())
+        .nest("/api", routes::discover::router())
+        .nest("/api", routes::drafts::router())
+        .nest("/api", routes::forks::router())
+        .nest("/api", routes::hastebin::router())
+        .nest("/api", routes::languages::router())
         .nest("/api", routes::pastes::router())
+        .nest("/api", routes::raw::router())
+        .nest("/api", routes::revisions::router())
+        .nest("/api", routes::search::router())
+        .nest("/api", routes::sessions::router())
+        .nest("/api", routes::tags::router())
         .nest("/api", routes::users::router())
         .route_layer(CorsLayer::permissive());
 
@@ -0,0 +1,167 @@
+use crate::json::{Error, JsonResponse};
+
+use axum::http::StatusCode;
+use base64::{decode_config, encode_config, URL_SAFE_NO_PAD};
+use serde::Serialize;
+
+/// The amount of items in a page when no limit is given.
+pub const DEFAULT_LIMIT: u32 = 25;
+/// The maximum amount of items in a page.
+pub const MAX_LIMIT: u32 = 100;
+
+/// A page of results, along with the cursors to pass in to retrieve the surrounding pages.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // None if this is the last page
+    pub next_cursor: Option<String>,
+    // None if this is the first page, or if the endpoint can't paginate backwards
+    pub prev_cursor: Option<String>,
+    // The total amount of items across all pages, if the endpoint keeps track of it
+    pub total: Option<u32>,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            prev_cursor: self.prev_cursor,
+            total: self.total,
+        }
+    }
+}
+
+fn invalid_cursor() -> JsonResponse<Error> {
+    JsonResponse(
+        StatusCode::BAD_REQUEST,
+        Error {
+            message: "Invalid cursor".to_string(),
+        },
+    )
+}
+
+/// A position in a list that is ordered by `(key, id)` in descending order.
+///
+/// Cursors are opaque to clients. They are scoped to the ordering they were created for, so that
+/// a cursor from one ordering can't be used with another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub key: i64,
+    pub id: String,
+}
+
+impl Cursor {
+    pub fn encode(&self, scope: &str) -> String {
+        encode_config(
+            format!("{}:{}:{}", scope, self.key, self.id),
+            URL_SAFE_NO_PAD,
+        )
+    }
+
+    pub fn decode(cursor: &str, scope: &str) -> Result<Self, JsonResponse<Error>> {
+        let decoded = decode_config(cursor, URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid_cursor)?;
+
+        // IDs never contain colons, but scopes might
+        let (rest, id) = decoded.rsplit_once(':').ok_or_else(invalid_cursor)?;
+        let (cursor_scope, key) = rest.rsplit_once(':').ok_or_else(invalid_cursor)?;
+
+        if cursor_scope != scope {
+            return Err(invalid_cursor());
+        }
+
+        Ok(Self {
+            key: key.parse().map_err(|_| invalid_cursor())?,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Describes which page of a list ordered by `(key, id)` to retrieve.
+///
+/// Queries should select the items after the cursor in descending order, or if `backwards` is set,
+/// the items before the cursor in ascending order. Either way, they should fetch
+/// [`Pagination::fetch_limit`] items so that it is known whether there are more.
+#[derive(Clone, Debug)]
+pub struct Pagination {
+    pub scope: String,
+    pub cursor: Option<Cursor>,
+    pub backwards: bool,
+    pub limit: u32,
+}
+
+impl Pagination {
+    pub fn new(
+        scope: impl Into<String>,
+        before: Option<String>,
+        after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Self, JsonResponse<Error>> {
+        let scope = scope.into();
+        let (cursor, backwards) = match (before, after) {
+            (Some(_), Some(_)) => {
+                return Err(JsonResponse(
+                    StatusCode::BAD_REQUEST,
+                    Error {
+                        message: "Only one of before and after may be specified".to_string(),
+                    },
+                ))
+            }
+            (Some(before), None) => (Some(Cursor::decode(&before, &scope)?), true),
+            (None, Some(after)) => (Some(Cursor::decode(&after, &scope)?), false),
+            (None, None) => (None, false),
+        };
+
+        Ok(Self {
+            scope,
+            cursor,
+            backwards,
+            limit: limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        })
+    }
+
+    pub fn key(&self) -> Option<i64> {
+        self.cursor.as_ref().map(|cursor| cursor.key)
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.cursor.as_ref().map(|cursor| cursor.id.as_str())
+    }
+
+    /// The amount of items to fetch, which is one more than the limit to tell if there are more.
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit as i64 + 1
+    }
+
+    /// Builds a page out of the items fetched for it, in the order they were fetched.
+    /// `cursor_of` returns the cursor pointing at an item.
+    pub fn paginate<T>(
+        &self,
+        mut items: Vec<T>,
+        total: Option<u32>,
+        cursor_of: impl Fn(&T) -> Cursor,
+    ) -> Page<T> {
+        let has_more = items.len() > self.limit as usize;
+        items.truncate(self.limit as usize);
+
+        // Paging backwards from a cursor means that there is always a next page
+        let (has_prev, has_next) = if self.backwards {
+            items.reverse();
+            (has_more, true)
+        } else {
+            (self.cursor.is_some(), has_more)
+        };
+
+        let encode = |item: Option<&T>| item.map(|item| cursor_of(item).encode(&self.scope));
+
+        Page {
+            next_cursor: has_next.then(|| encode(items.last())).flatten(),
+            prev_cursor: has_prev.then(|| encode(items.first())).flatten(),
+            items,
+            total,
+        }
+    }
+}
@@ -3,7 +3,7 @@ use crate::routes::{Error, JsonResponse};
 use axum::{
     body::Body,
     extract::ConnectInfo,
-    http::{header::FORWARDED, Request, StatusCode},
+    http::{header::FORWARDED, Extensions, HeaderMap, Request, StatusCode},
     response::{IntoResponse, Response},
 };
 use forwarded_header_value::{ForwardedHeaderValue, Identifier};
@@ -86,7 +86,7 @@ where
     }
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
-        let ip = match get_ip(&req) {
+        let ip = match get_ip(req.headers(), req.extensions()) {
             Some(ip) => ip,
             None => {
                 return Box::pin(async {
@@ -120,9 +120,7 @@ impl<S> Layer<S> for RatelimitLayer {
 }
 
 // Implmentation from https://github.com/imbolc/axum-client-ip/blob/main/src/lib.rs
-fn get_ip(req: &Request<Body>) -> Option<IpAddr> {
-    let headers = req.headers();
-
+pub fn get_ip(headers: &HeaderMap, extensions: &Extensions) -> Option<IpAddr> {
     headers
         .get("x-forwarded-for")
         .and_then(|hv| hv.to_str().ok())
@@ -150,7 +148,7 @@ fn get_ip(req: &Request<Body>) -> Option<IpAddr> {
             })
         })
         .or_else(|| {
-            req.extensions()
+            extensions
                 .get::<ConnectInfo<SocketAddr>>()
                 .map(|ConnectInfo(addr)| addr.ip())
         })
@@ -0,0 +1,687 @@
+use super::{Authorization, JsonResponse};
+use crate::{
+    auth::generate_id,
+    get_pool,
+    json::Error,
+    routes::{
+        pastes::{
+            authorize_paste, is_author, validate_password, GetPasteQuery, PastePreview,
+            PasteVisibility,
+        },
+        users::into_sanitized_paste,
+    },
+};
+
+use argon2_async::{hash, verify};
+use axum::{
+    extract::{Json, Path, Query},
+    handler::Handler,
+    http::StatusCode,
+    routing::{get, put},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+/// The maximum amount of collections a single user can have at once.
+pub const MAX_COLLECTIONS: i64 = 50;
+
+/// The maximum amount of pastes a single collection can hold.
+pub const MAX_COLLECTION_PASTES: i64 = 100;
+
+#[derive(Debug, Serialize)]
+pub struct Collection {
+    pub id: String,
+    pub user_id: String,
+    pub username: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub visibility: PasteVisibility,
+    pub pastes: Vec<PastePreview>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectionPreview {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub visibility: PasteVisibility,
+    pub paste_count: u32,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionPayload {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub visibility: PasteVisibility,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EditCollectionPayload {
+    pub name: Option<String>,
+    // An empty string removes the description
+    pub description: Option<String>,
+    pub visibility: Option<PasteVisibility>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderCollectionPayload {
+    // Every paste in the collection, in their new order
+    pub pastes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectionResponse {
+    pub id: String,
+}
+
+fn collection_not_found() -> JsonResponse<Error> {
+    JsonResponse(
+        StatusCode::NOT_FOUND,
+        Error {
+            message: "Collection not found".to_string(),
+        },
+    )
+}
+
+fn validate_name(name: &str) -> Result<(), JsonResponse<Error>> {
+    let len = name.chars().count();
+
+    if !(1..=64).contains(&len) {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "Collection names must be between 1 and 64 characters long".to_string(),
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+/// Ensures that the authorized user (if any) is allowed to view a collection, following the same
+/// rules as pastes.
+async fn check_collection_access(
+    auth: &Option<Authorization>,
+    user_id: &str,
+    visibility: i16,
+    hashed_password: Option<String>,
+    password: Option<String>,
+) -> Result<(), JsonResponse<Error>> {
+    if matches!(auth, Some(Authorization(u)) if u == user_id) || visibility >= 2 {
+        return Ok(());
+    }
+
+    if visibility == 0 {
+        return Err(JsonResponse(
+            StatusCode::UNAUTHORIZED,
+            Error {
+                message: "You do not have permission to view this collection".to_string(),
+            },
+        ));
+    }
+
+    let (password, hashed_password) = password.zip(hashed_password).ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Error {
+                message: r#"Please provide a "password" query parameter containing this collection's password."#.to_string(),
+            },
+        )
+    })?;
+
+    if !verify(password, hashed_password).await? {
+        return Err(JsonResponse(
+            StatusCode::UNAUTHORIZED,
+            Error {
+                message: "Incorrect password".to_string(),
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+/// Pastes are shown to anyone who can view the collection as long as they could also open the
+/// paste through its link, without a password and without burning it.
+fn sanitize_collection_paste(auth: &Option<Authorization>, preview: PastePreview) -> PastePreview {
+    let linkable = matches!(
+        preview.visibility,
+        PasteVisibility::Unlisted | PasteVisibility::Discoverable
+    ) && !preview.burn_after_read;
+
+    if linkable || is_author(auth, &preview.author_id) {
+        preview
+    } else {
+        into_sanitized_paste(preview)
+    }
+}
+
+/// Locks a collection for the rest of the transaction, ensuring that it is owned by the user.
+async fn lock_collection(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+    user_id: &str,
+) -> Result<(), JsonResponse<Error>> {
+    let owner_id = sqlx::query!(
+        "SELECT user_id FROM collections WHERE id = $1 FOR UPDATE",
+        id,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(collection_not_found)?
+    .user_id;
+
+    if owner_id != user_id {
+        return Err(JsonResponse(
+            StatusCode::FORBIDDEN,
+            Error {
+                message: "You do not have permission to edit this collection".to_string(),
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+async fn touch_collection(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+) -> Result<(), JsonResponse<Error>> {
+    sqlx::query!(
+        "UPDATE collections SET updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+        id,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// GET /users/:user_id/collections
+///
+/// # Note
+/// Only discoverable collections are listed, unless they are your own.
+pub async fn list_user_collections(
+    auth: Option<Authorization>,
+    Path(user_id): Path<String>,
+) -> Result<JsonResponse<Vec<CollectionPreview>>, JsonResponse<Error>> {
+    let collections = sqlx::query!(
+        r#"
+        SELECT
+            collections.*,
+            (
+                SELECT COUNT(*) FROM collection_pastes
+                JOIN pastes ON pastes.id = collection_pastes.paste_id
+                WHERE
+                    collection_id = collections.id
+                    AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            ) AS paste_count
+        FROM
+            collections
+        WHERE
+            user_id = $1 AND (visibility = 3 OR user_id = $2)
+        ORDER BY created_at DESC
+    "#,
+        user_id,
+        auth.as_ref().map(|Authorization(a)| a),
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    Ok(JsonResponse::ok(
+        collections
+            .into_iter()
+            .map(|collection| CollectionPreview {
+                id: collection.id,
+                user_id: collection.user_id,
+                name: collection.name,
+                description: collection.description,
+                visibility: PasteVisibility::from(collection.visibility as u8),
+                paste_count: collection.paste_count.unwrap_or(0) as u32,
+                created_at: collection.created_at.timestamp(),
+                updated_at: collection.updated_at.timestamp(),
+            })
+            .collect(),
+    ))
+}
+
+/// GET /users/me/collections
+pub async fn list_self_collections(
+    auth: Authorization,
+) -> Result<JsonResponse<Vec<CollectionPreview>>, JsonResponse<Error>> {
+    list_user_collections(Some(auth.clone()), Path(auth.0)).await
+}
+
+/// POST /users/me/collections
+///
+/// # Limits
+/// - Maximum 50 collections per user
+pub async fn create_collection(
+    Authorization(user_id): Authorization,
+    Json(payload): Json<CollectionPayload>,
+) -> Result<JsonResponse<CollectionResponse>, JsonResponse<Error>> {
+    validate_name(&payload.name)?;
+
+    let password = if payload.visibility == PasteVisibility::Protected {
+        validate_password(&payload.password)?;
+        Some(hash(payload.password.unwrap_or_default()).await?)
+    } else {
+        None
+    };
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    let count = sqlx::query!(
+        "SELECT COUNT(*) AS count FROM collections WHERE user_id = $1",
+        user_id,
+    )
+    .fetch_one(&mut transaction)
+    .await?
+    .count
+    .unwrap_or(0);
+
+    if count >= MAX_COLLECTIONS {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: format!(
+                    "You already have {} collections, which is the maximum. Delete some first.",
+                    count,
+                ),
+            },
+        ));
+    }
+
+    let id = generate_id::<12>();
+    let description = payload
+        .description
+        .filter(|description| !description.is_empty());
+
+    sqlx::query!(
+        "
+        INSERT INTO collections (id, user_id, name, description, visibility, password)
+        VALUES ($1, $2, $3, $4, $5, $6)
+    ",
+        id,
+        user_id,
+        payload.name,
+        description,
+        payload.visibility as i16,
+        password,
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(JsonResponse(StatusCode::CREATED, CollectionResponse { id }))
+}
+
+/// GET /collections/:id
+///
+/// # Note
+/// Pastes that the user wouldn't be able to open through their link are returned sanitized.
+pub async fn get_collection(
+    auth: Option<Authorization>,
+    Path(id): Path<String>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<JsonResponse<Collection>, JsonResponse<Error>> {
+    let db = get_pool();
+
+    let collection = sqlx::query!(
+        "
+        SELECT collections.*, users.username
+        FROM collections
+        JOIN users ON users.id = collections.user_id
+        WHERE collections.id = $1
+    ",
+        id,
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(collection_not_found)?;
+
+    check_collection_access(
+        &auth,
+        &collection.user_id,
+        collection.visibility,
+        collection.password,
+        query.password,
+    )
+    .await?;
+
+    let pastes = query_paste_previews!(
+        r#"
+            entry.idx::BIGINT AS "sort_key!",
+            NULL::TIMESTAMPTZ AS starred_at
+        FROM
+            collection_pastes AS entry
+        JOIN pastes ON pastes.id = entry.paste_id
+        "#,
+        r#"
+        WHERE
+            entry.collection_id = $1
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        ORDER BY entry.idx ASC
+    "#,
+        id,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(PastePreview::from)
+    .map(|paste| sanitize_collection_paste(&auth, paste))
+    .collect();
+
+    Ok(JsonResponse::ok(Collection {
+        id,
+        user_id: collection.user_id,
+        username: collection.username,
+        name: collection.name,
+        description: collection.description,
+        visibility: PasteVisibility::from(collection.visibility as u8),
+        pastes,
+        created_at: collection.created_at.timestamp(),
+        updated_at: collection.updated_at.timestamp(),
+    }))
+}
+
+/// PATCH /collections/:id
+pub async fn edit_collection(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+    Json(payload): Json<EditCollectionPayload>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    if let Some(name) = &payload.name {
+        validate_name(name)?;
+    }
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    lock_collection(&mut transaction, &id, &user_id).await?;
+
+    let collection = sqlx::query!(
+        "SELECT name, description, visibility, password FROM collections WHERE id = $1",
+        id,
+    )
+    .fetch_one(&mut transaction)
+    .await?;
+
+    let visibility = payload
+        .visibility
+        .unwrap_or_else(|| PasteVisibility::from(collection.visibility as u8));
+
+    let password = if visibility != PasteVisibility::Protected {
+        None
+    } else if payload.password.is_none()
+        && collection.visibility == PasteVisibility::Protected as i16
+    {
+        // Keep the existing password
+        collection.password
+    } else {
+        validate_password(&payload.password)?;
+        Some(hash(payload.password.unwrap_or_default()).await?)
+    };
+
+    let description = match payload.description {
+        Some(description) if description.is_empty() => None,
+        Some(description) => Some(description),
+        None => collection.description,
+    };
+
+    sqlx::query!(
+        "
+        UPDATE collections
+        SET
+            name = $2,
+            description = $3,
+            visibility = $4,
+            password = $5,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+    ",
+        id,
+        payload.name.unwrap_or(collection.name),
+        description,
+        visibility as i16,
+        password,
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /collections/:id
+///
+/// # Note
+/// This only deletes the collection. The pastes in it are left untouched.
+pub async fn delete_collection(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    lock_collection(&mut transaction, &id, &user_id).await?;
+
+    sqlx::query!("DELETE FROM collections WHERE id = $1", id)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /collections/:id/pastes/:paste_id
+///
+/// # Limits
+/// - Maximum 100 pastes per collection
+///
+/// # Note
+/// The paste is added to the end of the collection. Any paste you are able to view can be added,
+/// and adding a paste that is already in the collection does nothing.
+pub async fn add_collection_paste(
+    Authorization(user_id): Authorization,
+    Path((id, paste_id)): Path<(String, String)>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    authorize_paste(
+        &Some(Authorization(user_id.clone())),
+        &paste_id,
+        query.password,
+    )
+    .await?;
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    lock_collection(&mut transaction, &id, &user_id).await?;
+
+    let entries = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) AS "count!",
+            MAX(idx) AS last_idx,
+            COALESCE(BOOL_OR(paste_id = $2), false) AS "exists!"
+        FROM collection_pastes
+        WHERE collection_id = $1
+    "#,
+        id,
+        paste_id,
+    )
+    .fetch_one(&mut transaction)
+    .await?;
+
+    if entries.exists {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    if entries.count >= MAX_COLLECTION_PASTES {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: format!(
+                    "This collection already has {} pastes, which is the maximum",
+                    entries.count,
+                ),
+            },
+        ));
+    }
+
+    sqlx::query!(
+        "INSERT INTO collection_pastes (collection_id, paste_id, idx) VALUES ($1, $2, $3)",
+        id,
+        paste_id,
+        entries.last_idx.map_or(0, |idx| idx + 1),
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    touch_collection(&mut transaction, &id).await?;
+    transaction.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /collections/:id/pastes/:paste_id
+pub async fn remove_collection_paste(
+    Authorization(user_id): Authorization,
+    Path((id, paste_id)): Path<(String, String)>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    lock_collection(&mut transaction, &id, &user_id).await?;
+
+    let idx = sqlx::query!(
+        "DELETE FROM collection_pastes WHERE collection_id = $1 AND paste_id = $2 RETURNING idx",
+        id,
+        paste_id,
+    )
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Paste is not in this collection".to_string(),
+            },
+        )
+    })?
+    .idx;
+
+    // Close the gap, otherwise positions would keep growing as pastes are added and removed.
+    // Deleting a paste outright still leaves a gap behind, which reordering the collection closes.
+    sqlx::query!(
+        "UPDATE collection_pastes SET idx = idx - 1 WHERE collection_id = $1 AND idx > $2",
+        id,
+        idx,
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    touch_collection(&mut transaction, &id).await?;
+    transaction.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /collections/:id/pastes
+///
+/// # Note
+/// The new order must list every paste in the collection exactly once.
+pub async fn reorder_collection_pastes(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+    Json(payload): Json<ReorderCollectionPayload>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    lock_collection(&mut transaction, &id, &user_id).await?;
+
+    let mut current = sqlx::query!(
+        "SELECT paste_id FROM collection_pastes WHERE collection_id = $1",
+        id,
+    )
+    .fetch_all(&mut transaction)
+    .await?
+    .into_iter()
+    .map(|entry| entry.paste_id)
+    .collect::<Vec<_>>();
+
+    let mut requested = payload.pastes.clone();
+    current.sort_unstable();
+    requested.sort_unstable();
+
+    if current != requested {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "The new order must contain every paste in the collection exactly once"
+                    .to_string(),
+            },
+        ));
+    }
+
+    sqlx::query!(
+        "
+        UPDATE collection_pastes
+        SET idx = (entry.idx - 1)::SMALLINT
+        FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS entry(paste_id, idx)
+        WHERE collection_id = $1 AND collection_pastes.paste_id = entry.paste_id
+    ",
+        id,
+        &payload.pastes,
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    touch_collection(&mut transaction, &id).await?;
+    transaction.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/users/me/collections",
+            get(list_self_collections.layer(ratelimit!(5, 5)))
+                .post(create_collection.layer(ratelimit!(2, 5))),
+        )
+        .route(
+            "/users/:id/collections",
+            get(list_user_collections.layer(ratelimit!(5, 5))),
+        )
+        .route(
+            "/collections/:id/pastes/:paste_id",
+            put(add_collection_paste.layer(ratelimit!(10, 10)))
+                .delete(remove_collection_paste.layer(ratelimit!(10, 10))),
+        )
+        .route(
+            "/collections/:id/pastes",
+            put(reorder_collection_pastes.layer(ratelimit!(5, 5))),
+        )
+        .route(
+            "/collections/:id",
+            get(get_collection.layer(ratelimit!(10, 15)))
+                .patch(edit_collection.layer(ratelimit!(2, 5)))
+                .delete(delete_collection.layer(ratelimit!(3, 6))),
+        )
+}
@@ -0,0 +1,139 @@
+use super::JsonResponse;
+use crate::{
+    get_pool,
+    json::Error,
+    pagination::{Cursor, Page, Pagination},
+    routes::pastes::PastePreview,
+};
+
+use axum::{extract::Query, handler::Handler, routing::get, Router};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoverSort {
+    #[default]
+    Newest,
+    Stars,
+    Views,
+    // Most stars received within the window
+    Trending,
+}
+
+impl DiscoverSort {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::Stars => "stars",
+            Self::Views => "views",
+            Self::Trending => "trending",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrendingWindow {
+    Day,
+    #[default]
+    Week,
+    Month,
+}
+
+impl TrendingWindow {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+
+    pub fn duration(self) -> Duration {
+        match self {
+            Self::Day => Duration::days(1),
+            Self::Week => Duration::weeks(1),
+            Self::Month => Duration::days(30),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DiscoverQuery {
+    #[serde(default)]
+    pub sort: DiscoverSort,
+    // Only used when sorting by trending
+    #[serde(default)]
+    pub window: TrendingWindow,
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// GET /pastes/discover
+///
+/// # Limits
+/// - At most 100 pastes per page
+///
+/// # Note
+/// Burn-after-read pastes are never listed, since listing them would reveal their first file.
+pub async fn discover_pastes(
+    Query(query): Query<DiscoverQuery>,
+) -> Result<JsonResponse<Page<PastePreview>>, JsonResponse<Error>> {
+    let scope = match query.sort {
+        DiscoverSort::Trending => format!("discover.trending.{}", query.window.as_str()),
+        sort => format!("discover.{}", sort.as_str()),
+    };
+    let pagination = Pagination::new(scope, None, query.cursor, query.limit)?;
+
+    let pastes = query_paste_previews!(
+        r#"
+            s.score AS "sort_key!",
+            NULL::TIMESTAMPTZ AS starred_at
+        FROM
+            pastes
+        "#,
+        r#"
+        CROSS JOIN LATERAL (
+            SELECT CASE $1
+                WHEN 'stars' THEN (SELECT COUNT(*) FROM stars WHERE paste_id = pastes.id)
+                WHEN 'views' THEN pastes.views::BIGINT
+                WHEN 'trending' THEN (
+                    SELECT COUNT(*) FROM stars
+                    WHERE paste_id = pastes.id AND stars.created_at > $2
+                )
+                ELSE (EXTRACT(EPOCH FROM pastes.created_at) * 1000000)::BIGINT
+            END AS score
+        ) AS s
+        WHERE
+            visibility = 3
+            AND NOT burn_after_read
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            AND ($1 != 'trending' OR s.score > 0)
+            AND ($3::BIGINT IS NULL OR (s.score, pastes.id) < ($3, $4))
+        ORDER BY s.score DESC, pastes.id DESC
+        LIMIT $5
+        "#,
+        query.sort.as_str(),
+        Utc::now() - query.window.duration(),
+        pagination.key(),
+        pagination.id(),
+        pagination.fetch_limit(),
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    let page = pagination.paginate(pastes, None, |paste| Cursor {
+        key: paste.sort_key,
+        id: paste.id.clone(),
+    });
+
+    Ok(JsonResponse::ok(page.map(PastePreview::from)))
+}
+
+pub fn router() -> Router {
+    Router::new().route(
+        "/pastes/discover",
+        get(discover_pastes.layer(ratelimit!(5, 5))),
+    )
+}
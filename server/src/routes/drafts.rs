@@ -0,0 +1,414 @@
+use super::{Authorization, JsonResponse};
+use crate::{
+    auth::generate_id,
+    get_pool,
+    json::Error,
+    routes::pastes::{
+        create_paste, insert_files, resolve_languages, resolve_stored_language, validate_files,
+        validate_tags, File, PastePayload, PasteResponse, PasteVisibility,
+    },
+};
+
+use axum::{
+    extract::{Json, Path},
+    handler::Handler,
+    http::StatusCode,
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+
+/// The maximum amount of drafts a single user can have at once.
+pub const MAX_DRAFTS: i64 = 50;
+
+#[derive(Debug, Serialize)]
+pub struct Draft {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub visibility: PasteVisibility,
+    pub files: Vec<File>,
+    pub expires_in: Option<u64>,
+    pub burn_after_read: bool,
+    pub tags: Vec<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DraftPreview {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub visibility: PasteVisibility,
+    pub file_count: u32,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Like a [`PastePayload`], but every field is optional so that incomplete pastes can be saved.
+#[derive(Debug, Deserialize)]
+pub struct DraftPayload {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub visibility: PasteVisibility,
+    #[serde(default)]
+    pub files: Vec<File>,
+    pub expires_in: Option<u64>,
+    #[serde(default)]
+    pub burn_after_read: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PublishDraftPayload {
+    // Passwords are never stored in drafts, so they must be provided when publishing
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DraftResponse {
+    pub id: String,
+}
+
+/// Validates a draft, normalizing its languages and tags in place.
+fn validate_draft(payload: &mut DraftPayload) -> Result<(), JsonResponse<Error>> {
+    // Drafts may be incomplete, but are still subject to the same size limits as pastes
+    if !payload.files.is_empty() {
+        validate_files(&payload.files)?;
+    }
+
+    // Languages are only inferred once the draft is published, since filenames may still change
+    resolve_languages(&mut payload.files, false)?;
+
+    payload.tags = validate_tags(std::mem::take(&mut payload.tags))?;
+
+    if payload
+        .expires_in
+        .is_some_and(|expires_in| i64::try_from(expires_in).is_err())
+    {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "Paste expiry must be a valid time in the future".to_string(),
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+fn draft_not_found() -> JsonResponse<Error> {
+    JsonResponse(
+        StatusCode::NOT_FOUND,
+        Error {
+            message: "Draft not found".to_string(),
+        },
+    )
+}
+
+/// GET /users/me/drafts
+pub async fn list_drafts(
+    Authorization(user_id): Authorization,
+) -> Result<JsonResponse<Vec<DraftPreview>>, JsonResponse<Error>> {
+    let drafts = sqlx::query!(
+        r#"
+        SELECT
+            drafts.*,
+            (SELECT COUNT(*) FROM draft_files WHERE draft_id = drafts.id) AS file_count
+        FROM
+            drafts
+        WHERE
+            user_id = $1
+        ORDER BY updated_at DESC
+    "#,
+        user_id,
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    Ok(JsonResponse::ok(
+        drafts
+            .into_iter()
+            .map(|draft| DraftPreview {
+                id: draft.id,
+                name: draft.name,
+                description: draft.description,
+                visibility: PasteVisibility::from(draft.visibility as u8),
+                file_count: draft.file_count.unwrap_or(0) as u32,
+                created_at: draft.created_at.timestamp(),
+                updated_at: draft.updated_at.timestamp(),
+            })
+            .collect(),
+    ))
+}
+
+/// POST /users/me/drafts
+///
+/// # Limits
+/// - Maximum 50 drafts per user
+/// - Same file limits as POST /pastes, although drafts may have no files
+pub async fn create_draft(
+    Authorization(user_id): Authorization,
+    Json(mut payload): Json<DraftPayload>,
+) -> Result<JsonResponse<DraftResponse>, JsonResponse<Error>> {
+    validate_draft(&mut payload)?;
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    let count = sqlx::query!(
+        "SELECT COUNT(*) AS count FROM drafts WHERE user_id = $1",
+        user_id,
+    )
+    .fetch_one(&mut transaction)
+    .await?
+    .count
+    .unwrap_or(0);
+
+    if count >= MAX_DRAFTS {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: format!(
+                    "You already have {} drafts, which is the maximum. Publish or delete some first.",
+                    count,
+                ),
+            },
+        ));
+    }
+
+    let id = generate_id::<12>();
+
+    sqlx::query!(
+        "
+        INSERT INTO drafts
+            (id, user_id, name, description, visibility, expires_in, burn_after_read, tags)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    ",
+        id,
+        user_id,
+        payload.name,
+        payload.description,
+        payload.visibility as i16,
+        payload.expires_in.map(|expires_in| expires_in as i64),
+        payload.burn_after_read,
+        &payload.tags,
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    insert_files(&mut transaction, "draft_files", &id, payload.files).await?;
+
+    transaction.commit().await?;
+
+    Ok(JsonResponse(StatusCode::CREATED, DraftResponse { id }))
+}
+
+/// GET /users/me/drafts/:id
+pub async fn get_draft(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+) -> Result<JsonResponse<Draft>, JsonResponse<Error>> {
+    let db = get_pool();
+
+    let draft = sqlx::query!(
+        "SELECT * FROM drafts WHERE id = $1 AND user_id = $2",
+        id,
+        user_id,
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(draft_not_found)?;
+
+    let files = sqlx::query!(
+        "SELECT * FROM draft_files WHERE draft_id = $1 ORDER BY idx ASC",
+        id,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|record| File {
+        filename: record.filename,
+        content: record.content,
+        language: record.language,
+    })
+    .collect();
+
+    Ok(JsonResponse::ok(Draft {
+        id,
+        name: draft.name,
+        description: draft.description,
+        visibility: PasteVisibility::from(draft.visibility as u8),
+        files,
+        expires_in: draft.expires_in.map(|expires_in| expires_in as u64),
+        burn_after_read: draft.burn_after_read,
+        tags: draft.tags,
+        created_at: draft.created_at.timestamp(),
+        updated_at: draft.updated_at.timestamp(),
+    }))
+}
+
+/// PUT /users/me/drafts/:id
+///
+/// # Note
+/// This replaces the entire draft, and is meant to be called periodically to autosave.
+pub async fn update_draft(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+    Json(mut payload): Json<DraftPayload>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    validate_draft(&mut payload)?;
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    let rows_affected = sqlx::query!(
+        "
+        UPDATE drafts
+        SET
+            name = $3,
+            description = $4,
+            visibility = $5,
+            expires_in = $6,
+            burn_after_read = $7,
+            tags = $8,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND user_id = $2
+    ",
+        id,
+        user_id,
+        payload.name,
+        payload.description,
+        payload.visibility as i16,
+        payload.expires_in.map(|expires_in| expires_in as i64),
+        payload.burn_after_read,
+        &payload.tags,
+    )
+    .execute(&mut transaction)
+    .await?
+    .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(draft_not_found());
+    }
+
+    sqlx::query!("DELETE FROM draft_files WHERE draft_id = $1", id)
+        .execute(&mut transaction)
+        .await?;
+
+    insert_files(&mut transaction, "draft_files", &id, payload.files).await?;
+
+    transaction.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /users/me/drafts/:id
+pub async fn delete_draft(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM drafts WHERE id = $1 AND user_id = $2",
+        id,
+        user_id,
+    )
+    .execute(get_pool())
+    .await?
+    .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(draft_not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /users/me/drafts/:id/publish
+///
+/// # Note
+/// The draft goes through the same validation as POST /pastes, and is deleted once published.
+pub async fn publish_draft(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+    payload: Option<Json<PublishDraftPayload>>,
+) -> Result<JsonResponse<PasteResponse>, JsonResponse<Error>> {
+    let Json(PublishDraftPayload { password }) = payload.unwrap_or_default();
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    // Lock the draft so that it can't be published twice
+    let draft = sqlx::query!(
+        "SELECT * FROM drafts WHERE id = $1 AND user_id = $2 FOR UPDATE",
+        id,
+        user_id,
+    )
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(draft_not_found)?;
+
+    let files = sqlx::query!(
+        "SELECT * FROM draft_files WHERE draft_id = $1 ORDER BY idx ASC",
+        id,
+    )
+    .fetch_all(&mut transaction)
+    .await?
+    .into_iter()
+    .map(|record| File {
+        filename: record.filename,
+        content: record.content,
+        // The language may have been renamed by Linguist since the draft was saved
+        language: resolve_stored_language(record.language),
+    })
+    .collect();
+
+    let paste_id = create_paste(
+        &mut transaction,
+        Some(user_id),
+        PastePayload {
+            name: draft.name,
+            description: draft.description,
+            visibility: PasteVisibility::from(draft.visibility as u8),
+            password,
+            files,
+            expires_in: draft.expires_in.map(|expires_in| expires_in as u64),
+            expires_at: None,
+            burn_after_read: draft.burn_after_read,
+            tags: draft.tags,
+        },
+    )
+    .await?;
+
+    sqlx::query!("DELETE FROM drafts WHERE id = $1", id)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(JsonResponse(
+        StatusCode::CREATED,
+        PasteResponse { id: paste_id },
+    ))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/users/me/drafts/:id/publish",
+            post(publish_draft.layer(ratelimit!(2, 5))),
+        )
+        .route(
+            "/users/me/drafts/:id",
+            get(get_draft.layer(ratelimit!(5, 5)))
+                .put(update_draft.layer(ratelimit!(10, 10)))
+                .delete(delete_draft.layer(ratelimit!(3, 6))),
+        )
+        .route(
+            "/users/me/drafts",
+            get(list_drafts.layer(ratelimit!(5, 5))).post(create_draft.layer(ratelimit!(2, 5))),
+        )
+}
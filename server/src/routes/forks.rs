@@ -0,0 +1,174 @@
+use super::{Authorization, JsonResponse};
+use crate::{
+    get_pool,
+    json::Error,
+    routes::{
+        pastes::{
+            authorize_paste, create_paste, resolve_stored_language, File, GetPasteQuery,
+            PastePayload, PastePreview, PasteResponse, PasteVisibility,
+        },
+        users::sanitize_paste,
+    },
+};
+
+use axum::{
+    extract::{Json, Path, Query},
+    handler::Handler,
+    http::StatusCode,
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ForkPastePayload {
+    // Defaults to the name of the original paste
+    pub name: Option<String>,
+    // Defaults to the visibility of the original paste
+    pub visibility: Option<PasteVisibility>,
+    pub password: Option<String>,
+}
+
+/// POST /pastes/:id/fork
+///
+/// # Note
+/// The fork is a copy of the current revision and tags of the paste, and starts out with its own
+/// history. Passwords are not copied, so forking a password protected paste without changing its
+/// visibility requires a new password.
+pub async fn fork_paste(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+    Query(query): Query<GetPasteQuery>,
+    payload: Option<Json<ForkPastePayload>>,
+) -> Result<JsonResponse<PasteResponse>, JsonResponse<Error>> {
+    authorize_paste(&Some(Authorization(user_id.clone())), &id, query.password).await?;
+
+    let Json(ForkPastePayload {
+        name,
+        visibility,
+        password,
+    }) = payload.unwrap_or_default();
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    let paste = sqlx::query!(
+        "SELECT name, description, visibility FROM pastes WHERE id = $1",
+        id,
+    )
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Paste not found".to_string(),
+            },
+        )
+    })?;
+
+    let tags = sqlx::query!(
+        "SELECT tag FROM paste_tags WHERE paste_id = $1 ORDER BY tag",
+        id,
+    )
+    .fetch_all(&mut transaction)
+    .await?
+    .into_iter()
+    .map(|record| record.tag)
+    .collect();
+
+    let files = sqlx::query!(
+        "SELECT * FROM files WHERE paste_id = $1 ORDER BY idx ASC",
+        id,
+    )
+    .fetch_all(&mut transaction)
+    .await?
+    .into_iter()
+    .map(|record| File {
+        filename: record.filename,
+        content: record.content,
+        language: resolve_stored_language(record.language),
+    })
+    .collect();
+
+    let fork_id = create_paste(
+        &mut transaction,
+        Some(user_id),
+        PastePayload {
+            name: Some(name.unwrap_or(paste.name)),
+            description: paste.description,
+            visibility: visibility.unwrap_or_else(|| PasteVisibility::from(paste.visibility as u8)),
+            password,
+            files,
+            expires_in: None,
+            expires_at: None,
+            burn_after_read: false,
+            tags,
+        },
+    )
+    .await?;
+
+    sqlx::query!(
+        "UPDATE pastes SET forked_from = $2 WHERE id = $1",
+        fork_id,
+        id,
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(JsonResponse(
+        StatusCode::CREATED,
+        PasteResponse { id: fork_id },
+    ))
+}
+
+/// GET /pastes/:id/forks
+///
+/// # Note
+/// Only discoverable forks are listed, along with any forks owned by the authorized user.
+pub async fn get_paste_forks(
+    auth: Option<Authorization>,
+    Path(id): Path<String>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<JsonResponse<Vec<PastePreview>>, JsonResponse<Error>> {
+    authorize_paste(&auth, &id, query.password).await?;
+
+    let forks = query_paste_previews!(
+        r#"
+            (EXTRACT(EPOCH FROM pastes.created_at) * 1000000)::BIGINT AS "sort_key!",
+            NULL::TIMESTAMPTZ AS starred_at
+        FROM
+            pastes
+        "#,
+        r#"
+        WHERE
+            forked_from = $1
+            AND (visibility = 3 OR author_id = $2)
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        ORDER BY created_at ASC
+        "#,
+        id,
+        auth.as_ref().map(|Authorization(a)| a),
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    Ok(JsonResponse::ok(
+        forks
+            .into_iter()
+            .map(PastePreview::from)
+            .map(|paste| sanitize_paste(&auth, paste))
+            .collect(),
+    ))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/pastes/:id/forks",
+            get(get_paste_forks.layer(ratelimit!(5, 5))),
+        )
+        .route("/pastes/:id/fork", post(fork_paste.layer(ratelimit!(2, 5))))
+}
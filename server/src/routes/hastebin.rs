@@ -0,0 +1,136 @@
+// Endpoints compatible with the [Hastebin](https://github.com/toptal/haste-server) API, so that
+// existing Hastebin clients can use Turbine by pointing them at the `/api` base URI.
+
+use super::{Authorization, JsonResponse};
+use crate::{
+    get_pool,
+    json::Error,
+    routes::pastes::{
+        create_paste, read_paste_files, File, GetPasteQuery, PastePayload, PasteVisibility,
+    },
+};
+
+use axum::{
+    extract::{Path, Query},
+    handler::Handler,
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct HastebinKey {
+    pub key: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HastebinDocument {
+    pub key: String,
+    pub data: String,
+}
+
+/// Hastebin clients may append a file extension to the key for syntax highlighting.
+fn strip_extension(key: &str) -> &str {
+    key.split_once('.').map_or(key, |(key, _)| key)
+}
+
+/// Reads the first file of a paste, see [`read_paste_files`].
+async fn read_document(
+    auth: &Option<Authorization>,
+    key: &str,
+    password: Option<String>,
+) -> Result<String, JsonResponse<Error>> {
+    let mut files = read_paste_files(auth, key, Some(0), password).await?;
+
+    Ok(files.remove(0).content)
+}
+
+/// POST /documents
+///
+/// # Note
+/// The request body is the raw content of the paste. This creates an unlisted paste with a single
+/// unnamed file, and is subject to the same file limits as POST /pastes.
+pub async fn post_document(
+    auth: Option<Authorization>,
+    content: String,
+) -> Result<JsonResponse<HastebinKey>, JsonResponse<Error>> {
+    if content.is_empty() {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "No content provided".to_string(),
+            },
+        ));
+    }
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    let key = create_paste(
+        &mut transaction,
+        auth.map(|auth| auth.0),
+        PastePayload {
+            name: None,
+            description: None,
+            visibility: PasteVisibility::Unlisted,
+            password: None,
+            files: vec![File {
+                filename: None,
+                content,
+                language: None,
+            }],
+            expires_in: None,
+            expires_at: None,
+            burn_after_read: false,
+            tags: Vec::new(),
+        },
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    // Hastebin responds with 200 OK rather than 201 Created, which some clients rely on
+    Ok(JsonResponse::ok(HastebinKey { key }))
+}
+
+/// GET /documents/:key
+pub async fn get_document(
+    auth: Option<Authorization>,
+    Path(key): Path<String>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<JsonResponse<HastebinDocument>, JsonResponse<Error>> {
+    let key = strip_extension(&key).to_string();
+    let data = read_document(&auth, &key, query.password).await?;
+
+    Ok(JsonResponse::ok(HastebinDocument { key, data }))
+}
+
+/// GET /raw/:key
+pub async fn get_raw_document(
+    auth: Option<Authorization>,
+    Path(key): Path<String>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<Response, JsonResponse<Error>> {
+    let data = read_document(&auth, strip_extension(&key), query.password).await?;
+
+    Ok((
+        [(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        )],
+        data,
+    )
+        .into_response())
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/documents/:key",
+            get(get_document.layer(ratelimit!(10, 15))),
+        )
+        .route("/documents", post(post_document.layer(ratelimit!(2, 5))))
+        .route("/raw/:key", get(get_raw_document.layer(ratelimit!(10, 15))))
+}
@@ -0,0 +1,205 @@
+use super::JsonResponse;
+use crate::{
+    get_pool,
+    json::Error,
+    languages::{detect_file, get_registry, languages_etag, LANGUAGES_JSON},
+};
+
+use axum::{
+    extract::{Json, Path},
+    handler::Handler,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use linguist::{DetectionMethod, Language};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub pastes: u32,
+    pub files: u32,
+}
+
+#[derive(Deserialize)]
+pub struct DetectLanguagePayload {
+    pub filename: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DetectedLanguage {
+    pub language: Option<String>,
+    pub method: Option<DetectionMethod>,
+    pub confidence: f64,
+}
+
+/// Whether an `If-None-Match` header matches the given ETag.
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// GET /languages
+///
+/// # Note
+/// This is the `languages.json` the server was built with. Responds with 304 Not Modified if the
+/// `If-None-Match` header matches its ETag.
+pub async fn get_languages(headers: HeaderMap) -> Response {
+    let etag = HeaderValue::from_static(languages_etag());
+    // Languages only change when the server is redeployed, so let clients reuse them for a while
+    let cache_control = HeaderValue::from_static("public, max-age=3600");
+
+    if etag_matches(&headers, languages_etag()) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, etag), (CACHE_CONTROL, cache_control)],
+        )
+            .into_response();
+    }
+
+    (
+        [
+            (CONTENT_TYPE, HeaderValue::from_static("application/json")),
+            (ETAG, etag),
+            (CACHE_CONTROL, cache_control),
+        ],
+        LANGUAGES_JSON,
+    )
+        .into_response()
+}
+
+/// GET /languages/:name
+///
+/// # Note
+/// The name can also be an alias of the language, and is case-insensitive.
+pub async fn get_language(
+    Path(name): Path<String>,
+) -> Result<JsonResponse<&'static Language>, JsonResponse<Error>> {
+    get_registry()
+        .find(name.trim())
+        .map(JsonResponse::ok)
+        .ok_or_else(|| {
+            JsonResponse(
+                StatusCode::NOT_FOUND,
+                Error {
+                    message: "Language not found".to_string(),
+                },
+            )
+        })
+}
+
+/// GET /languages/stats
+///
+/// # Note
+/// Only files of discoverable pastes are counted. Languages without any are left out.
+pub async fn get_language_stats() -> Result<JsonResponse<Vec<LanguageStats>>, JsonResponse<Error>>
+{
+    let stats = sqlx::query!(
+        r#"
+        SELECT
+            language AS "language!",
+            COUNT(DISTINCT paste_id) AS "pastes!",
+            COUNT(*) AS "files!"
+        FROM files
+        JOIN pastes ON pastes.id = files.paste_id
+        WHERE
+            language IS NOT NULL
+            AND visibility = 3
+            AND NOT burn_after_read
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        GROUP BY language
+        ORDER BY COUNT(DISTINCT paste_id) DESC, language ASC
+        "#,
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    Ok(JsonResponse::ok(
+        stats
+            .into_iter()
+            .map(|record| LanguageStats {
+                language: record.language,
+                pastes: record.pastes as u32,
+                files: record.files as u32,
+            })
+            .collect(),
+    ))
+}
+
+/// POST /languages/detect
+///
+/// # Limits
+/// - Filename must be at most 64 characters
+/// - Content must be at most 2 MiB
+pub async fn detect_language(
+    Json(payload): Json<DetectLanguagePayload>,
+) -> Result<JsonResponse<DetectedLanguage>, JsonResponse<Error>> {
+    if let Some(filename) = &payload.filename {
+        if filename.chars().count() > 64 {
+            return Err(JsonResponse(
+                StatusCode::BAD_REQUEST,
+                Error {
+                    message: format!(
+                        "The filename has a length of {}, which surpasses the maximum of 64",
+                        filename.chars().count(),
+                    ),
+                },
+            ));
+        }
+    }
+
+    if payload.content.len() > 2 * 1024 * 1024 {
+        return Err(JsonResponse(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Error {
+                message: format!(
+                    "The content has a size of {} bytes, which surpasses the maximum of 2 MiB",
+                    payload.content.len(),
+                ),
+            },
+        ));
+    }
+
+    let detection = detect_file(payload.filename.as_deref(), &payload.content);
+
+    Ok(JsonResponse::ok(match detection {
+        Some(detection) => DetectedLanguage {
+            language: Some(detection.language.name.clone()),
+            method: Some(detection.method),
+            confidence: detection.confidence,
+        },
+        None => DetectedLanguage {
+            language: None,
+            method: None,
+            confidence: 0.0,
+        },
+    }))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/languages", get(get_languages.layer(ratelimit!(5, 5))))
+        .route(
+            "/languages/detect",
+            post(detect_language.layer(ratelimit!(5, 5))),
+        )
+        .route(
+            "/languages/stats",
+            get(get_language_stats.layer(ratelimit!(5, 5))),
+        )
+        .route(
+            "/languages/:name",
+            get(get_language.layer(ratelimit!(10, 5))),
+        )
+}
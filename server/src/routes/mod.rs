@@ -1,4 +1,71 @@
+macro_rules! ratelimit {
+    ($rate:expr, $per:expr) => {{
+        ::tower::ServiceBuilder::new()
+            .layer(::axum::error_handling::HandleErrorLayer::new(
+                |e| async move {
+                    $crate::json::JsonResponse(
+                        ::axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        $crate::json::Error {
+                            message: format!("Internal error: {}", e),
+                        },
+                    )
+                },
+            ))
+            .layer(::tower::buffer::BufferLayer::new(1024))
+            .layer($crate::RatelimitLayer($rate, $per))
+    }};
+}
+
+/// Queries [`PastePreviewRow`](crate::routes::users::PastePreviewRow)s.
+///
+/// `$select` finishes the select list with the `sort_key` and `starred_at` columns, followed by a
+/// FROM clause that brings `pastes` into scope. The author and first file of each paste are joined
+/// right after it, and `$rest` continues from there.
+macro_rules! query_paste_previews {
+    ($select:literal, $rest:literal $(, $args:expr)* $(,)?) => {
+        ::sqlx::query_as!(
+            $crate::routes::users::PastePreviewRow,
+            r#"
+            SELECT
+                pastes.id,
+                pastes.name,
+                pastes.description,
+                pastes.author_id,
+                u.username AS "author_name?",
+                pastes.visibility,
+                pastes.created_at,
+                pastes.views,
+                pastes.expires_at,
+                pastes.burn_after_read,
+                f.filename AS "filename?",
+                f.content AS "content!",
+                f.language AS "language?",
+                (SELECT COUNT(*) FROM stars WHERE paste_id = pastes.id) AS "stars!",
+            "# + $select + r#"
+            LEFT JOIN LATERAL (
+                SELECT username FROM users WHERE users.id = pastes.author_id
+            ) AS u ON username IS NOT NULL
+            LEFT JOIN LATERAL (
+                SELECT * FROM files WHERE files.paste_id = pastes.id AND files.idx = 0
+            ) AS f ON true
+            "# + $rest,
+            $($args),*
+        )
+    };
+}
+
+pub mod collections;
+pub mod discover;
+pub mod drafts;
+pub mod forks;
+pub mod hastebin;
+pub mod languages;
 pub mod pastes;
+pub mod raw;
+pub mod revisions;
+pub mod search;
+pub mod sessions;
+pub mod tags;
 pub mod users;
 
 pub use crate::{
@@ -6,39 +73,90 @@ pub use crate::{
     json::{Error, JsonResponse},
 };
 
+use crate::ratelimit::get_ip;
 use axum::{
     async_trait,
     body::Body,
     extract::{FromRequest, RequestParts},
-    http::header::AUTHORIZATION,
+    http::header::{AUTHORIZATION, USER_AGENT},
 };
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Authorization(pub String);
 
+fn get_token(req: &RequestParts<Body>) -> Result<&str, JsonResponse<Error>> {
+    let auth_header = req.headers().get(AUTHORIZATION).ok_or_else(|| {
+        (
+            400,
+            Error {
+                message: "Missing 'Authorization' header".to_string(),
+            },
+        )
+    })?;
+
+    auth_header.to_str().map_err(|_| {
+        (
+            400,
+            Error {
+                message: "Authorization header is not valid UTF-8".to_string(),
+            },
+        )
+        .into()
+    })
+}
+
 #[async_trait]
 impl FromRequest<Body> for Authorization {
     type Rejection = JsonResponse<Error>;
 
     async fn from_request(req: &mut RequestParts<Body>) -> Result<Self, Self::Rejection> {
-        let auth_header = req.headers().get(AUTHORIZATION).ok_or_else(|| {
-            (
-                400,
-                Error {
-                    message: "Missing 'Authorization' header".to_string(),
-                },
-            )
-        })?;
-
-        let content = auth_header.to_str().map_err(|_| {
-            (
-                400,
-                Error {
-                    message: "Authorization header is not valid UTF-8".to_string(),
-                },
-            )
-        })?;
+        Ok(Self(resolve_token(get_token(req)?).await?))
+    }
+}
+
+/// Like [`Authorization`], but also keeps the token itself, for endpoints that act on the session
+/// the request was made with.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthorizedToken {
+    pub user_id: String,
+    pub token: String,
+}
 
-        Ok(Self(resolve_token(content).await?))
+#[async_trait]
+impl FromRequest<Body> for AuthorizedToken {
+    type Rejection = JsonResponse<Error>;
+
+    async fn from_request(req: &mut RequestParts<Body>) -> Result<Self, Self::Rejection> {
+        let token = get_token(req)?.to_string();
+
+        Ok(Self {
+            user_id: resolve_token(&token).await?,
+            token,
+        })
+    }
+}
+
+/// The user agent and IP address of the client making the request, which are recorded for new
+/// sessions.
+#[derive(Clone, Debug)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[async_trait]
+impl FromRequest<Body> for ClientInfo {
+    type Rejection = JsonResponse<Error>;
+
+    async fn from_request(req: &mut RequestParts<Body>) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            user_agent: req
+                .headers()
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                // Nothing needs more than this, and it's stored for every session
+                .map(|user_agent| user_agent.chars().take(512).collect()),
+            ip: get_ip(req.headers(), req.extensions()).map(|ip| ip.to_string()),
+        })
     }
 }
@@ -1,18 +1,34 @@
 use super::{Authorization, JsonResponse};
-use crate::{auth::generate_id, get_pool, json::Error, RatelimitLayer};
+use crate::{
+    auth::generate_id,
+    get_pool,
+    json::Error,
+    languages::{detect_file, resolve_language, INFER_MIN_CONFIDENCE},
+    routes::users::Timestamp,
+    upload::PasteBody,
+};
 
 use argon2_async::{hash, verify};
 use axum::{
-    error_handling::HandleErrorLayer,
     extract::{Json, Path, Query},
     handler::Handler,
     http::StatusCode,
     routing::{get, post},
     Router,
 };
+use chrono::{Duration, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use tower::{buffer::BufferLayer, ServiceBuilder};
+use sqlx::{Postgres, Transaction};
+
+/// The maximum amount of files a single paste can have.
+pub const MAX_FILES: usize = 16;
+
+/// The maximum size of a single file in bytes.
+pub const MAX_FILE_SIZE: usize = 2 * 1024 * 1024;
+
+/// The maximum amount of tags a single paste can have.
+pub const MAX_TAGS: usize = 10;
 
 #[derive(Copy, Clone, Debug, Default, Deserialize_repr, Serialize_repr, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -56,6 +72,11 @@ pub struct Paste {
     pub views: u32,
     pub stars: u32,
     pub starred: Option<bool>,
+    pub revision: u32,
+    pub expires_at: Option<i64>,
+    pub burn_after_read: bool,
+    pub forked_from: Option<String>,
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -72,6 +93,8 @@ pub struct PastePreview {
     pub stars: u32,
     pub available: bool,
     pub starred_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub burn_after_read: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -82,6 +105,13 @@ pub struct PastePayload {
     pub visibility: PasteVisibility,
     pub password: Option<String>,
     pub files: Vec<File>,
+    // Only one of these may be specified
+    pub expires_in: Option<u64>,
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub burn_after_read: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -89,57 +119,43 @@ pub struct PasteResponse {
     pub id: String,
 }
 
-#[derive(Deserialize)]
-pub struct GetPasteQuery {
-    password: Option<String>,
+#[derive(Debug, Deserialize)]
+pub struct EditPastePayload {
+    pub name: Option<String>,
+    // An empty string removes the description
+    pub description: Option<String>,
+    pub visibility: Option<PasteVisibility>,
+    pub password: Option<String>,
+    pub files: Option<Vec<File>>,
+    // Replaces all of the tags of the paste
+    pub tags: Option<Vec<String>>,
 }
 
-/// GET /pastes/:id
-pub async fn get_paste(
-    auth: Option<Authorization>,
-    Path(id): Path<String>,
-    Query(query): Query<GetPasteQuery>,
-) -> Result<JsonResponse<Paste>, JsonResponse<Error>> {
-    let db = get_pool();
+#[derive(Debug, Serialize)]
+pub struct EditPasteResponse {
+    pub revision: u32,
+}
 
-    let paste = sqlx::query!(
-        r#"
-        SELECT
-            pastes.*,
-            u.username AS "username?",
-            (SELECT COUNT(*) FROM stars WHERE paste_id = pastes.id) AS stars,
-            EXISTS(SELECT 1 FROM stars WHERE paste_id = pastes.id AND user_id = $2) AS starred
-        FROM
-            pastes
-        LEFT JOIN LATERAL (
-            SELECT username FROM users WHERE users.id = pastes.author_id
-        ) AS u ON username IS NOT NULL
-        WHERE
-            id = $1
-    "#,
-        id,
-        auth.as_ref().map(|Authorization(a)| a)
-    )
-    .fetch_optional(db)
-    .await?
-    .ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Error {
-                message: "Paste not found".to_string(),
-            },
-        )
-    })?;
+#[derive(Deserialize)]
+pub struct GetPasteQuery {
+    pub password: Option<String>,
+}
 
-    let views = sqlx::query!(
-        "UPDATE pastes SET views = views + 1 WHERE id = $1 RETURNING views",
-        id,
-    )
-    .fetch_one(db)
-    .await?
-    .views;
+/// Whether the authorized user (if any) is the author of a paste with the given author.
+pub fn is_author(auth: &Option<Authorization>, author_id: &Option<String>) -> bool {
+    matches!((auth, author_id), (Some(Authorization(u)), Some(a)) if u == a)
+}
 
-    if paste.visibility == 0 && auth.is_none() {
+/// Ensures that the authorized user (if any) is allowed to view a paste with the given
+/// author, visibility and hashed password, verifying `password` for protected pastes.
+pub async fn check_paste_access(
+    auth: &Option<Authorization>,
+    author_id: &Option<String>,
+    visibility: i16,
+    hashed_password: Option<String>,
+    password: Option<String>,
+) -> Result<(), JsonResponse<Error>> {
+    if visibility == 0 && auth.is_none() {
         return Err(JsonResponse(
             StatusCode::UNAUTHORIZED,
             Error {
@@ -148,17 +164,26 @@ pub async fn get_paste(
         ));
     }
 
-    let authorized = if let (Some(Authorization(u)), Some(author_id)) = (&auth, &paste.author_id) {
+    let authorized = if let (Some(Authorization(u)), Some(author_id)) = (auth, author_id) {
         u == author_id
     } else {
-        paste.visibility >= 2
+        visibility >= 2
     };
 
-    if paste.visibility == 1 && !authorized {
-        if let Some(password) = query.password {
+    if visibility == 0 && !authorized {
+        return Err(JsonResponse(
+            StatusCode::UNAUTHORIZED,
+            Error {
+                message: "You do not have permission to view this paste".to_string(),
+            },
+        ));
+    }
+
+    if visibility == 1 && !authorized {
+        if let Some(password) = password {
             if !verify(
                 password,
-                paste.password.clone().ok_or_else(|| {
+                hashed_password.ok_or_else(|| {
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
                         Error {
@@ -187,89 +212,97 @@ pub async fn get_paste(
         }
     }
 
-    let files = sqlx::query!(
-        "SELECT * FROM files WHERE paste_id = $1 ORDER BY idx ASC",
-        id
-    )
-    .fetch_all(db)
-    .await?
-    .into_iter()
-    .map(|record| File {
-        filename: record.filename,
-        content: record.content,
-        language: record.language,
-    })
-    .collect::<Vec<_>>();
-
-    Ok(JsonResponse::ok(Paste {
-        id,
-        author_id: paste.author_id,
-        author_name: paste.username,
-        name: paste.name,
-        description: paste.description,
-        visibility: PasteVisibility::from(paste.visibility as u8),
-        files,
-        created_at: paste.created_at.timestamp(),
-        stars: paste.stars.unwrap_or(0) as u32,
-        starred: auth.and_then(|_| paste.starred),
-        views: views as u32,
-    }))
+    Ok(())
 }
 
-/// POST /pastes
+/// Looks up the paste with the given ID and ensures that the authorized user (if any) is allowed
+/// to view it. See [`check_paste_access`].
 ///
-/// # Limits
-/// - 2 requests per 5 seconds (Scoped by IP)
-/// - Maximum 16 files
-/// - Each file has a maximum size of 2 MB
-pub async fn post_paste(
-    auth: Option<Authorization>,
-    Json(payload): Json<PastePayload>,
-) -> Result<JsonResponse<PasteResponse>, JsonResponse<Error>> {
-    if payload.visibility == PasteVisibility::Private && auth.is_none() {
+/// Burn-after-read pastes are only accessible to their author here, since they must be read
+/// through [`get_paste`] to be deleted.
+pub async fn authorize_paste(
+    auth: &Option<Authorization>,
+    id: &str,
+    password: Option<String>,
+) -> Result<(), JsonResponse<Error>> {
+    let paste = sqlx::query!(
+        "
+        SELECT author_id, visibility, password, burn_after_read
+        FROM pastes
+        WHERE id = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+    ",
+        id,
+    )
+    .fetch_optional(get_pool())
+    .await?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Paste not found".to_string(),
+            },
+        )
+    })?;
+
+    // Nothing authorized through here burns the paste, so reading it here would bypass burning it
+    if paste.burn_after_read && !is_author(auth, &paste.author_id) {
         return Err(JsonResponse(
-            StatusCode::BAD_REQUEST,
+            StatusCode::FORBIDDEN,
             Error {
-                message: "Authorization header required for private pastes".to_string(),
+                message: "This paste is deleted after it is read and can only be viewed once"
+                    .to_string(),
             },
         ));
     }
 
-    if payload.visibility == PasteVisibility::Protected {
-        if let Some(password) = &payload.password {
-            if password.chars().count() < 1 {
-                return Err(JsonResponse(
-                    StatusCode::BAD_REQUEST,
-                    Error {
-                        message: "Password field must be at least 1 character long".to_string(),
-                    },
-                ));
-            }
-        } else {
+    check_paste_access(
+        auth,
+        &paste.author_id,
+        paste.visibility,
+        paste.password,
+        password,
+    )
+    .await
+}
+
+pub fn validate_password(password: &Option<String>) -> Result<(), JsonResponse<Error>> {
+    if let Some(password) = password {
+        if password.chars().count() < 1 {
             return Err(JsonResponse(
                 StatusCode::BAD_REQUEST,
                 Error {
-                    message: "Missing password field in a paste with protected visibility"
-                        .to_string(),
+                    message: "Password field must be at least 1 character long".to_string(),
                 },
             ));
         }
+    } else {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "Missing password field with protected visibility".to_string(),
+            },
+        ));
     }
 
-    if payload.files.is_empty() {
+    Ok(())
+}
+
+pub fn validate_files(files: &[File]) -> Result<(), JsonResponse<Error>> {
+    if files.is_empty() {
         return Err(JsonResponse(
             StatusCode::BAD_REQUEST,
             Error {
                 message: "No files provided".to_string(),
             },
         ));
-    } else if payload.files.len() > 16 {
+    } else if files.len() > MAX_FILES {
         return Err(JsonResponse(
             StatusCode::BAD_REQUEST,
             Error {
                 message: format!(
-                    "Received {} files, which is greater than the maximum of 16",
-                    payload.files.len(),
+                    "Received {} files, which is greater than the maximum of {}",
+                    files.len(),
+                    MAX_FILES,
                 ),
             },
         ));
@@ -282,7 +315,7 @@ pub async fn post_paste(
             content,
             language,
         },
-    ) in payload.files.iter().enumerate()
+    ) in files.iter().enumerate()
     {
         for (name, entity, max_len) in [("filename", filename, 64), ("language", language, 32)] {
             if let Some(entity) = entity {
@@ -303,23 +336,476 @@ pub async fn post_paste(
             }
         }
 
-        if content.len() > 2 * 1024 * 1024 {
+        if content.len() > MAX_FILE_SIZE {
             return Err(JsonResponse(
                 StatusCode::PAYLOAD_TOO_LARGE,
                 Error {
                     message: format!(
-                        "The file at index {} (0-indexed) has a size of {} bytes, which surpasses the maximum of 2 MiB",
+                        "The file at index {} (0-indexed) has a size of {} bytes, which surpasses the maximum of {} MiB",
                         i,
                         content.len(),
+                        MAX_FILE_SIZE / 1024 / 1024,
                     ),
                 }
             ));
         }
     }
 
-    let id = generate_id::<12>();
+    Ok(())
+}
+
+/// Replaces the language of each file with the canonical name of the language. Files without a
+/// language have it inferred from their filename and content if `infer` is set, and are left as
+/// plain text otherwise or when the language can't be detected confidently.
+pub fn resolve_languages(files: &mut [File], infer: bool) -> Result<(), JsonResponse<Error>> {
+    for (i, file) in files.iter_mut().enumerate() {
+        file.language = match file.language.take().filter(|language| !language.is_empty()) {
+            Some(language) => Some(
+                resolve_language(&language)
+                    .ok_or_else(|| {
+                        (
+                            StatusCode::BAD_REQUEST,
+                            Error {
+                                message: format!(
+                                    "The language of the file at index {} (0-indexed), {:?}, is not a known language",
+                                    i, language,
+                                ),
+                            },
+                        )
+                    })?
+                    .to_string(),
+            ),
+            None if infer => detect_file(file.filename.as_deref(), &file.content)
+                .filter(|detection| detection.confidence >= INFER_MIN_CONFIDENCE)
+                .map(|detection| detection.language.name.clone()),
+            None => None,
+        };
+    }
+
+    Ok(())
+}
+
+/// Resolves a language read back from the database into its canonical name. Languages weren't
+/// always validated and can be renamed by Linguist, so unknown ones are dropped to be inferred again
+/// by [`resolve_languages`] instead of being rejected.
+pub fn resolve_stored_language(language: Option<String>) -> Option<String> {
+    language
+        .and_then(|language| resolve_language(&language))
+        .map(ToString::to_string)
+}
+
+/// Resolves the `expires_in` and `expires_at` fields of a paste payload into an expiry time.
+fn resolve_expiry(
+    expires_in: Option<u64>,
+    expires_at: Option<i64>,
+) -> Result<Option<Timestamp>, JsonResponse<Error>> {
+    let expires_at = match (expires_in, expires_at) {
+        (Some(_), Some(_)) => {
+            return Err(JsonResponse(
+                StatusCode::BAD_REQUEST,
+                Error {
+                    message: "Only one of expires_in and expires_at may be provided".to_string(),
+                },
+            ))
+        }
+        (Some(seconds), None) => i64::try_from(seconds)
+            .ok()
+            .and_then(|seconds| Utc::now().checked_add_signed(Duration::seconds(seconds))),
+        (None, Some(timestamp)) => Utc.timestamp_opt(timestamp, 0).single(),
+        (None, None) => return Ok(None),
+    };
+
+    match expires_at {
+        Some(expires_at) if expires_at > Utc::now() => Ok(Some(expires_at)),
+        _ => Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "Paste expiry must be a valid time in the future".to_string(),
+            },
+        )),
+    }
+}
+
+/// Inserts the files of a paste, or of anything stored the same way (e.g. `draft_files`), into
+/// `table`.
+pub async fn insert_files(
+    transaction: &mut Transaction<'_, Postgres>,
+    table: &'static str,
+    id: &str,
+    files: Vec<File>,
+) -> Result<(), JsonResponse<Error>> {
+    let (filenames, (content, languages)) = files
+        .into_iter()
+        .map(|file| (file.filename, (file.content, file.language)))
+        .unzip::<_, _, Vec<_>, (Vec<_>, Vec<_>)>();
+
+    sqlx::query(&format!(
+        "
+        INSERT INTO {table}
+        SELECT $1, out.*
+        FROM UNNEST($2, $3, $4, $5)
+        AS out(idx, filename, content, languages)
+    ",
+    ))
+    .bind(id)
+    .bind((0..filenames.len() as i16).collect::<Vec<_>>())
+    .bind(filenames)
+    .bind(content)
+    .bind(languages)
+    .execute(&mut *transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Normalizes tags to lowercase and removes duplicates, then validates them.
+///
+/// # Limits
+/// - Maximum 10 tags
+/// - Each tag is between 1 and 32 characters long, and only contains alphanumeric characters, _ and -
+pub fn validate_tags(tags: Vec<String>) -> Result<Vec<String>, JsonResponse<Error>> {
+    let mut normalized = Vec::<String>::with_capacity(tags.len());
+
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+
+    if normalized.len() > MAX_TAGS {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: format!(
+                    "Received {} tags, which is greater than the maximum of {}",
+                    normalized.len(),
+                    MAX_TAGS,
+                ),
+            },
+        ));
+    }
+
+    for tag in &normalized {
+        if tag.is_empty() || tag.chars().count() > 32 {
+            return Err(JsonResponse(
+                StatusCode::BAD_REQUEST,
+                Error {
+                    message: "Tags must be between 1 and 32 characters long".to_string(),
+                },
+            ));
+        }
+
+        if !tag
+            .chars()
+            .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '-'))
+        {
+            return Err(JsonResponse(
+                StatusCode::BAD_REQUEST,
+                Error {
+                    message: format!(
+                        "Tag {:?} must only contain alphanumeric characters, _ and -",
+                        tag,
+                    ),
+                },
+            ));
+        }
+
+        if tag.starts_with('-') || tag.ends_with('-') {
+            return Err(JsonResponse(
+                StatusCode::BAD_REQUEST,
+                Error {
+                    message: format!("Tag {:?} must not start or end with -", tag),
+                },
+            ));
+        }
+    }
+
+    Ok(normalized)
+}
+
+async fn insert_tags(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+    tags: &[String],
+) -> Result<(), JsonResponse<Error>> {
+    sqlx::query!(
+        "INSERT INTO paste_tags SELECT $1, UNNEST($2::TEXT[])",
+        id,
+        tags,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Snapshots the current name, description and files of a paste as a new immutable revision,
+/// returning the number of the new revision.
+async fn record_revision(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+    name: &str,
+    description: &Option<String>,
+) -> Result<i32, JsonResponse<Error>> {
+    let revision = sqlx::query!(
+        r#"
+        INSERT INTO paste_revisions (paste_id, revision, name, description)
+        SELECT $1, COALESCE(MAX(revision) + 1, 0), $2, $3
+        FROM paste_revisions
+        WHERE paste_id = $1
+        RETURNING revision
+    "#,
+        id,
+        name,
+        description.as_ref(),
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .revision;
+
+    sqlx::query!(
+        "
+        INSERT INTO file_revisions
+        SELECT paste_id, $2, idx, filename, content, language
+        FROM files
+        WHERE paste_id = $1
+    ",
+        id,
+        revision,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    Ok(revision)
+}
+
+/// Reads the files of a paste, or only the file at `idx` if given, deleting the paste afterwards
+/// if `burn` is set. This does not check whether the user is allowed to view the paste.
+async fn take_files(
+    id: &str,
+    idx: Option<u16>,
+    burn: bool,
+) -> Result<Vec<File>, JsonResponse<Error>> {
+    let mut transaction = get_pool().begin().await?;
+
+    let files = sqlx::query!(
+        "
+        SELECT * FROM files
+        WHERE paste_id = $1 AND ($2::SMALLINT IS NULL OR idx = $2)
+        ORDER BY idx ASC
+    ",
+        id,
+        idx.map(|idx| idx as i16),
+    )
+    .fetch_all(&mut transaction)
+    .await?
+    .into_iter()
+    .map(|record| File {
+        filename: record.filename,
+        content: record.content,
+        language: record.language,
+    })
+    .collect::<Vec<_>>();
+
+    // Pastes always have at least one file, so this only happens when `idx` is out of range.
+    // Returning here rolls back the transaction so that the paste isn't burned.
+    if files.is_empty() {
+        return Err(JsonResponse(
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "File not found".to_string(),
+            },
+        ));
+    }
+
+    if burn {
+        // Only the first reader is able to delete the paste. Concurrent readers wait on the row
+        // lock and find nothing left to delete once this transaction commits.
+        let burned = sqlx::query!("DELETE FROM pastes WHERE id = $1", id)
+            .execute(&mut transaction)
+            .await?
+            .rows_affected();
+
+        if burned == 0 {
+            return Err(JsonResponse(
+                StatusCode::NOT_FOUND,
+                Error {
+                    message: "Paste not found".to_string(),
+                },
+            ));
+        }
+    }
+
+    transaction.commit().await?;
+
+    Ok(files)
+}
+
+/// Reads the files of a paste with the same access rules as GET /pastes/:id, including counting a
+/// view and burning the paste if it is deleted after it is read.
+pub async fn read_paste_files(
+    auth: &Option<Authorization>,
+    id: &str,
+    idx: Option<u16>,
+    password: Option<String>,
+) -> Result<Vec<File>, JsonResponse<Error>> {
+    let db = get_pool();
+
+    let paste = sqlx::query!(
+        "
+        SELECT author_id, visibility, password, burn_after_read
+        FROM pastes
+        WHERE id = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+    ",
+        id,
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Paste not found".to_string(),
+            },
+        )
+    })?;
+
+    check_paste_access(
+        auth,
+        &paste.author_id,
+        paste.visibility,
+        paste.password,
+        password,
+    )
+    .await?;
+
+    sqlx::query!("UPDATE pastes SET views = views + 1 WHERE id = $1", id)
+        .execute(db)
+        .await?;
+
+    take_files(
+        id,
+        idx,
+        paste.burn_after_read && !is_author(auth, &paste.author_id),
+    )
+    .await
+}
+
+/// GET /pastes/:id
+pub async fn get_paste(
+    auth: Option<Authorization>,
+    Path(id): Path<String>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<JsonResponse<Paste>, JsonResponse<Error>> {
     let db = get_pool();
-    let mut transaction = db.begin().await?;
+
+    let paste = sqlx::query!(
+        r#"
+        SELECT
+            pastes.*,
+            u.username AS "username?",
+            (SELECT COUNT(*) FROM stars WHERE paste_id = pastes.id) AS stars,
+            EXISTS(SELECT 1 FROM stars WHERE paste_id = pastes.id AND user_id = $2) AS starred,
+            (SELECT MAX(revision) FROM paste_revisions WHERE paste_id = pastes.id) AS revision,
+            ARRAY(SELECT tag FROM paste_tags WHERE paste_id = pastes.id ORDER BY tag) AS "tags!"
+        FROM
+            pastes
+        LEFT JOIN LATERAL (
+            SELECT username FROM users WHERE users.id = pastes.author_id
+        ) AS u ON username IS NOT NULL
+        WHERE
+            id = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+    "#,
+        id,
+        auth.as_ref().map(|Authorization(a)| a)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Paste not found".to_string(),
+            },
+        )
+    })?;
+
+    let views = sqlx::query!(
+        "UPDATE pastes SET views = views + 1 WHERE id = $1 RETURNING views",
+        id,
+    )
+    .fetch_optional(db)
+    .await?
+    // The paste may have been burned by a concurrent reader since it was fetched
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Paste not found".to_string(),
+            },
+        )
+    })?
+    .views;
+
+    check_paste_access(
+        &auth,
+        &paste.author_id,
+        paste.visibility,
+        paste.password,
+        query.password,
+    )
+    .await?;
+
+    let burn = paste.burn_after_read && !is_author(&auth, &paste.author_id);
+    let files = take_files(&id, None, burn).await?;
+
+    Ok(JsonResponse::ok(Paste {
+        id,
+        author_id: paste.author_id,
+        author_name: paste.username,
+        name: paste.name,
+        description: paste.description,
+        visibility: PasteVisibility::from(paste.visibility as u8),
+        files,
+        created_at: paste.created_at.timestamp(),
+        stars: paste.stars.unwrap_or(0) as u32,
+        starred: auth.and_then(|_| paste.starred),
+        views: views as u32,
+        revision: paste.revision.unwrap_or(0) as u32,
+        expires_at: paste.expires_at.map(|e| e.timestamp()),
+        burn_after_read: paste.burn_after_read,
+        forked_from: paste.forked_from,
+        tags: paste.tags,
+    }))
+}
+
+/// Validates and inserts a new paste within the given transaction, returning its ID.
+pub async fn create_paste(
+    transaction: &mut Transaction<'_, Postgres>,
+    author_id: Option<String>,
+    payload: PastePayload,
+) -> Result<String, JsonResponse<Error>> {
+    if payload.visibility == PasteVisibility::Private && author_id.is_none() {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "Authorization header required for private pastes".to_string(),
+            },
+        ));
+    }
+
+    if payload.visibility == PasteVisibility::Protected {
+        validate_password(&payload.password)?;
+    }
+
+    let mut files = payload.files;
+    validate_files(&files)?;
+    resolve_languages(&mut files, true)?;
+    let tags = validate_tags(payload.tags)?;
+    let expires_at = resolve_expiry(payload.expires_in, payload.expires_at)?;
+
+    let id = generate_id::<12>();
 
     let password = if let Some(password) = payload.password {
         Some(hash(password).await?)
@@ -327,43 +813,201 @@ pub async fn post_paste(
         None
     };
 
+    let name = payload.name.unwrap_or_else(|| "Untitled Paste".to_string());
+
     sqlx::query!(
-        "INSERT INTO pastes VALUES ($1, $2, $3, $4, $5, $6)",
+        "
+        INSERT INTO pastes
+            (id, author_id, name, description, visibility, password, expires_at, burn_after_read)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    ",
         id,
-        auth.map(|auth| auth.0),
-        payload.name.unwrap_or_else(|| "Untitled Paste".to_string()),
+        author_id,
+        name,
         payload.description,
         payload.visibility as i16,
         password,
+        expires_at,
+        payload.burn_after_read,
     )
-    .execute(&mut transaction)
+    .execute(&mut *transaction)
     .await?;
 
-    let (filenames, (content, languages)) = payload
-        .files
-        .into_iter()
-        .map(|file| (file.filename, (file.content, file.language)))
-        .unzip::<_, _, Vec<_>, (Vec<_>, Vec<_>)>();
+    insert_files(transaction, "files", &id, files).await?;
+    insert_tags(transaction, &id, &tags).await?;
+    record_revision(transaction, &id, &name, &payload.description).await?;
+
+    Ok(id)
+}
+
+/// POST /pastes
+///
+/// # Limits
+/// - 2 requests per 5 seconds (Scoped by IP)
+/// - Maximum 16 files
+/// - Each file has a maximum size of 2 MB
+///
+/// # Note
+/// This also accepts a `multipart/form-data` body, see [`crate::upload::read_multipart`].
+pub async fn post_paste(
+    auth: Option<Authorization>,
+    PasteBody(payload): PasteBody,
+) -> Result<JsonResponse<PasteResponse>, JsonResponse<Error>> {
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    let id = create_paste(&mut transaction, auth.map(|auth| auth.0), payload).await?;
+
+    transaction.commit().await?;
 
-    sqlx::query(
+    Ok(JsonResponse(StatusCode::CREATED, PasteResponse { id }))
+}
+
+/// PATCH /pastes/:id
+///
+/// # Limits
+/// - 2 requests per 5 seconds (Scoped by IP)
+/// - Same file limits as POST /pastes
+///
+/// # Note
+/// Changing the name, description or files of a paste records a new revision.
+/// Visibility, password and tag changes are applied to the paste without creating one.
+pub async fn edit_paste(
+    Authorization(user_id): Authorization,
+    Path(id): Path<String>,
+    Json(mut payload): Json<EditPastePayload>,
+) -> Result<JsonResponse<EditPasteResponse>, JsonResponse<Error>> {
+    if let Some(files) = &payload.files {
+        validate_files(files)?;
+    }
+    let tags = payload.tags.map(validate_tags).transpose()?;
+
+    let db = get_pool();
+    let mut transaction = db.begin().await?;
+
+    // Lock the paste so that concurrent edits can't claim the same revision number
+    let paste = sqlx::query!(
         "
-        INSERT INTO files
-        SELECT $1, out.*
-        FROM UNNEST($2, $3, $4, $5)
-        AS out(idx, filename, content, languages)
+        SELECT author_id, name, description, visibility, password
+        FROM pastes
+        WHERE id = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        FOR UPDATE
     ",
+        id,
+    )
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Paste not found".to_string(),
+            },
+        )
+    })?;
+
+    if paste.author_id != Some(user_id) {
+        return Err(JsonResponse(
+            StatusCode::FORBIDDEN,
+            Error {
+                message: "You do not have permission to edit this paste".to_string(),
+            },
+        ));
+    }
+
+    if let Some(files) = &mut payload.files {
+        // Clients send back the languages of files they didn't change, which may be stored ones
+        // that are no longer known
+        let stored = sqlx::query!(
+            "SELECT DISTINCT language FROM files WHERE paste_id = $1",
+            id
+        )
+        .fetch_all(&mut transaction)
+        .await?
+        .into_iter()
+        .filter_map(|record| record.language)
+        .collect::<Vec<_>>();
+
+        for file in files.iter_mut() {
+            if file.language.as_ref().is_some_and(|language| stored.contains(language)) {
+                file.language = resolve_stored_language(file.language.take());
+            }
+        }
+
+        resolve_languages(files, true)?;
+    }
+
+    let visibility = payload
+        .visibility
+        .unwrap_or_else(|| PasteVisibility::from(paste.visibility as u8));
+
+    let password = if visibility != PasteVisibility::Protected {
+        None
+    } else if payload.password.is_none() && paste.visibility == PasteVisibility::Protected as i16 {
+        // Keep the existing password
+        paste.password
+    } else {
+        validate_password(&payload.password)?;
+
+        if let Some(password) = payload.password {
+            Some(hash(password).await?)
+        } else {
+            None
+        }
+    };
+
+    let name = payload.name.unwrap_or_else(|| paste.name.clone());
+    let description = match payload.description {
+        Some(description) if description.is_empty() => None,
+        Some(description) => Some(description),
+        None => paste.description.clone(),
+    };
+    let edited = payload.files.is_some() || name != paste.name || description != paste.description;
+
+    sqlx::query!(
+        "UPDATE pastes SET name = $2, description = $3, visibility = $4, password = $5 WHERE id = $1",
+        id,
+        name,
+        description,
+        visibility as i16,
+        password,
     )
-    .bind(id.clone())
-    .bind((0..filenames.len() as i16).collect::<Vec<_>>())
-    .bind(filenames)
-    .bind(content)
-    .bind(languages)
     .execute(&mut transaction)
     .await?;
 
+    if let Some(files) = payload.files {
+        sqlx::query!("DELETE FROM files WHERE paste_id = $1", id)
+            .execute(&mut transaction)
+            .await?;
+
+        insert_files(&mut transaction, "files", &id, files).await?;
+    }
+
+    if let Some(tags) = tags {
+        sqlx::query!("DELETE FROM paste_tags WHERE paste_id = $1", id)
+            .execute(&mut transaction)
+            .await?;
+
+        insert_tags(&mut transaction, &id, &tags).await?;
+    }
+
+    let revision = if edited {
+        record_revision(&mut transaction, &id, &name, &description).await?
+    } else {
+        sqlx::query!(
+            r#"SELECT MAX(revision) AS "revision!" FROM paste_revisions WHERE paste_id = $1"#,
+            id,
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .revision
+    };
+
     transaction.commit().await?;
 
-    Ok(JsonResponse(StatusCode::CREATED, PasteResponse { id }))
+    Ok(JsonResponse::ok(EditPasteResponse {
+        revision: revision as u32,
+    }))
 }
 
 /// DELETE /pastes/:id
@@ -402,27 +1046,55 @@ pub async fn delete_paste(
     Ok(StatusCode::NO_CONTENT)
 }
 
-macro_rules! ratelimit {
-    ($rate:expr, $per:expr) => {{
-        ServiceBuilder::new()
-            .layer(HandleErrorLayer::new(|e| async move {
-                JsonResponse(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Error {
-                        message: format!("Internal error: {}", e),
-                    },
-                )
-            }))
-            .layer(BufferLayer::new(1024))
-            .layer(RatelimitLayer($rate, $per))
-    }};
-}
-
 pub fn router() -> Router {
     Router::new()
         .route(
             "/pastes/:id",
-            get(get_paste.layer(ratelimit!(10, 15))).delete(delete_paste.layer(ratelimit!(3, 6))),
+            get(get_paste.layer(ratelimit!(10, 15)))
+                .patch(edit_paste.layer(ratelimit!(2, 5)))
+                .delete(delete_paste.layer(ratelimit!(3, 6))),
         )
         .route("/pastes", post(post_paste.layer(ratelimit!(2, 5))))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth(user_id: &str) -> Option<Authorization> {
+        Some(Authorization(user_id.to_string()))
+    }
+
+    #[tokio::test]
+    async fn private_pastes_are_only_visible_to_their_author() {
+        let author_id = Some("author".to_string());
+
+        assert!(
+            check_paste_access(&auth("author"), &author_id, 0, None, None)
+                .await
+                .is_ok()
+        );
+
+        for auth in [None, auth("someone else")] {
+            let JsonResponse(status, _) = check_paste_access(&auth, &author_id, 0, None, None)
+                .await
+                .unwrap_err();
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn public_and_unlisted_pastes_are_visible_to_everyone() {
+        let author_id = Some("author".to_string());
+
+        for visibility in [2, 3] {
+            for auth in [None, auth("someone else"), auth("author")] {
+                assert!(
+                    check_paste_access(&auth, &author_id, visibility, None, None)
+                        .await
+                        .is_ok()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use super::{Authorization, JsonResponse};
+use crate::{
+    archive::{build_archive, ArchiveFormat},
+    json::Error,
+    routes::pastes::{read_paste_files, File, GetPasteQuery},
+};
+
+use axum::{
+    extract::{Path, Query},
+    handler::Handler,
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        HeaderValue,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use std::fmt::Write;
+
+#[derive(Deserialize)]
+pub struct ArchiveQuery {
+    #[serde(default)]
+    pub format: ArchiveFormat,
+    pub password: Option<String>,
+}
+
+/// The filename used for a file of a paste when it was not given one.
+pub fn default_filename(idx: usize) -> String {
+    format!("file{}.txt", idx + 1)
+}
+
+/// Builds a `Content-Disposition` header for the given filename, following RFC 6266. Clients that
+/// don't understand `filename*` fall back to an ASCII-only version of the name.
+pub fn content_disposition(kind: &str, filename: &str) -> HeaderValue {
+    let fallback = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    let mut encoded = String::with_capacity(filename.len());
+    for byte in filename.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => write!(encoded, "%{:02X}", byte).unwrap(),
+        }
+    }
+
+    HeaderValue::from_str(&format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        kind, fallback, encoded,
+    ))
+    .expect("header value should only contain visible ASCII")
+}
+
+fn raw_response(file: File, idx: usize) -> Response {
+    let filename = file.filename.unwrap_or_else(|| default_filename(idx));
+
+    (
+        [
+            (
+                CONTENT_TYPE,
+                HeaderValue::from_static("text/plain; charset=utf-8"),
+            ),
+            (
+                CONTENT_DISPOSITION,
+                content_disposition("inline", &filename),
+            ),
+        ],
+        file.content,
+    )
+        .into_response()
+}
+
+/// GET /pastes/:id/raw
+///
+/// # Note
+/// This is the raw content of the first file of the paste.
+pub async fn get_paste_raw(
+    auth: Option<Authorization>,
+    Path(id): Path<String>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<Response, JsonResponse<Error>> {
+    let mut files = read_paste_files(&auth, &id, Some(0), query.password).await?;
+
+    Ok(raw_response(files.remove(0), 0))
+}
+
+/// GET /pastes/:id/files/:idx/raw
+pub async fn get_file_raw(
+    auth: Option<Authorization>,
+    Path((id, idx)): Path<(String, u16)>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<Response, JsonResponse<Error>> {
+    let mut files = read_paste_files(&auth, &id, Some(idx), query.password).await?;
+
+    Ok(raw_response(files.remove(0), idx as usize))
+}
+
+/// GET /pastes/:id/archive
+///
+/// # Note
+/// Defaults to a zip archive. Unnamed and clashing filenames are renamed, see
+/// [`crate::archive::entry_names`].
+pub async fn get_paste_archive(
+    auth: Option<Authorization>,
+    Path(id): Path<String>,
+    Query(query): Query<ArchiveQuery>,
+) -> Result<Response, JsonResponse<Error>> {
+    let files = read_paste_files(&auth, &id, None, query.password).await?;
+    // Compressing large pastes is CPU-bound, so keep it off the async workers
+    let format = query.format;
+    let archive = tokio::task::spawn_blocking(move || build_archive(&files, format)).await??;
+
+    Ok((
+        [
+            (
+                CONTENT_TYPE,
+                HeaderValue::from_static(format.content_type()),
+            ),
+            (
+                CONTENT_DISPOSITION,
+                content_disposition("attachment", &format!("{}.{}", id, format.extension())),
+            ),
+        ],
+        archive,
+    )
+        .into_response())
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/pastes/:id/archive",
+            get(get_paste_archive.layer(ratelimit!(3, 10))),
+        )
+        .route(
+            "/pastes/:id/files/:idx/raw",
+            get(get_file_raw.layer(ratelimit!(10, 15))),
+        )
+        .route(
+            "/pastes/:id/raw",
+            get(get_paste_raw.layer(ratelimit!(10, 15))),
+        )
+}
@@ -0,0 +1,202 @@
+use super::{Authorization, JsonResponse};
+use crate::{
+    diff::{diff_files, FileDiff},
+    get_pool,
+    json::Error,
+    routes::pastes::{authorize_paste, File, GetPasteQuery},
+};
+
+use axum::{
+    extract::{Path, Query},
+    handler::Handler,
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+pub struct PasteRevision {
+    pub revision: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub files: Vec<File>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasteRevisionPreview {
+    pub revision: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub file_count: u32,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasteDiff {
+    pub from: u32,
+    pub to: u32,
+    pub files: Vec<FileDiff>,
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub from: u32,
+    // Defaults to the current revision
+    pub to: Option<u32>,
+    pub context: Option<usize>,
+    pub password: Option<String>,
+}
+
+/// GET /pastes/:id/revisions
+pub async fn get_paste_revisions(
+    auth: Option<Authorization>,
+    Path(id): Path<String>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<JsonResponse<Vec<PasteRevisionPreview>>, JsonResponse<Error>> {
+    authorize_paste(&auth, &id, query.password).await?;
+
+    let revisions = sqlx::query!(
+        r#"
+        SELECT
+            paste_revisions.*,
+            (
+                SELECT COUNT(*) FROM file_revisions
+                WHERE paste_id = paste_revisions.paste_id AND revision = paste_revisions.revision
+            ) AS file_count
+        FROM
+            paste_revisions
+        WHERE
+            paste_id = $1
+        ORDER BY revision ASC
+    "#,
+        id,
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    Ok(JsonResponse::ok(
+        revisions
+            .into_iter()
+            .map(|record| PasteRevisionPreview {
+                revision: record.revision as u32,
+                name: record.name,
+                description: record.description,
+                file_count: record.file_count.unwrap_or(0) as u32,
+                created_at: record.created_at.timestamp(),
+            })
+            .collect(),
+    ))
+}
+
+/// Fetches a single revision of a paste. This does not check whether the user is allowed to
+/// view the paste.
+pub async fn fetch_revision(id: &str, revision: u32) -> Result<PasteRevision, JsonResponse<Error>> {
+    let db = get_pool();
+
+    let record = sqlx::query!(
+        "SELECT * FROM paste_revisions WHERE paste_id = $1 AND revision = $2",
+        id,
+        revision as i32,
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Revision not found".to_string(),
+            },
+        )
+    })?;
+
+    let files = sqlx::query!(
+        "SELECT * FROM file_revisions WHERE paste_id = $1 AND revision = $2 ORDER BY idx ASC",
+        id,
+        revision as i32,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|record| File {
+        filename: record.filename,
+        content: record.content,
+        language: record.language,
+    })
+    .collect();
+
+    Ok(PasteRevision {
+        revision,
+        name: record.name,
+        description: record.description,
+        files,
+        created_at: record.created_at.timestamp(),
+    })
+}
+
+/// Returns the number of the latest revision of a paste.
+pub async fn current_revision(id: &str) -> Result<u32, JsonResponse<Error>> {
+    let revision = sqlx::query!(
+        r#"SELECT MAX(revision) AS "revision!" FROM paste_revisions WHERE paste_id = $1"#,
+        id,
+    )
+    .fetch_one(get_pool())
+    .await?
+    .revision;
+
+    Ok(revision as u32)
+}
+
+/// GET /pastes/:id/revisions/:revision
+pub async fn get_paste_revision(
+    auth: Option<Authorization>,
+    Path((id, revision)): Path<(String, u32)>,
+    Query(query): Query<GetPasteQuery>,
+) -> Result<JsonResponse<PasteRevision>, JsonResponse<Error>> {
+    authorize_paste(&auth, &id, query.password).await?;
+
+    Ok(JsonResponse::ok(fetch_revision(&id, revision).await?))
+}
+
+/// GET /pastes/:id/diff
+///
+/// # Limits
+/// - At most 20 lines of context
+pub async fn get_paste_diff(
+    auth: Option<Authorization>,
+    Path(id): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> Result<JsonResponse<PasteDiff>, JsonResponse<Error>> {
+    authorize_paste(&auth, &id, query.password).await?;
+
+    let to = match query.to {
+        Some(to) => to,
+        None => current_revision(&id).await?,
+    };
+
+    let old = fetch_revision(&id, query.from).await?;
+    let new = fetch_revision(&id, to).await?;
+
+    Ok(JsonResponse::ok(PasteDiff {
+        from: query.from,
+        to,
+        files: diff_files(&old.files, &new.files, query.context.unwrap_or(3).min(20)),
+    }))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/pastes/:id/diff",
+            get(get_paste_diff.layer(ratelimit!(5, 5))),
+        )
+        .route(
+            "/pastes/:id/revisions/:revision",
+            get(get_paste_revision.layer(ratelimit!(10, 15))),
+        )
+        .route(
+            "/pastes/:id/revisions",
+            get(get_paste_revisions.layer(ratelimit!(5, 5))),
+        )
+}
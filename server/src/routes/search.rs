@@ -0,0 +1,215 @@
+use super::{Authorization, JsonResponse};
+use crate::{
+    get_pool,
+    json::Error,
+    pagination::{Cursor, Page, Pagination},
+    routes::pastes::PastePreview,
+};
+
+use axum::{extract::Query, handler::Handler, http::StatusCode, routing::get, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Control characters that never appear in snippets, used to mark highlighted ranges
+const START_SEL: char = '\u{2}';
+const STOP_SEL: char = '\u{3}';
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub language: Option<String>,
+    // The ID of the author
+    pub author: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Highlight {
+    // Offsets are in characters, relative to the start of the snippet
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Snippet {
+    pub file_idx: u16,
+    pub content: String,
+    pub highlights: Vec<Highlight>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub paste: PastePreview,
+    pub snippet: Option<Snippet>,
+}
+
+/// Splits a headline generated by `ts_headline` into its text and highlighted ranges.
+fn parse_headline(headline: &str) -> (String, Vec<Highlight>) {
+    let mut content = String::with_capacity(headline.len());
+    let mut highlights = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+
+    for c in headline.chars() {
+        match c {
+            START_SEL => start = Some(offset),
+            STOP_SEL => {
+                if let Some(start) = start.take() {
+                    highlights.push(Highlight { start, end: offset });
+                }
+            }
+            c => {
+                content.push(c);
+                offset += 1;
+            }
+        }
+    }
+
+    (content, highlights)
+}
+
+/// GET /pastes/search
+///
+/// # Limits
+/// - Queries are at most 256 characters long
+/// - At most 100 results per page
+///
+/// # Note
+/// Only discoverable pastes and the authorized user's own pastes are searchable. Burn-after-read
+/// pastes of other users are excluded, since their snippets would reveal their content.
+pub async fn search_pastes(
+    auth: Option<Authorization>,
+    Query(query): Query<SearchQuery>,
+) -> Result<JsonResponse<Page<SearchResult>>, JsonResponse<Error>> {
+    let q = query.q.trim();
+    if q.is_empty() || q.chars().count() > 256 {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "Search query must be between 1 and 256 characters long".to_string(),
+            },
+        ));
+    }
+
+    let pagination = Pagination::new("search", None, query.cursor, query.limit)?;
+
+    let results = query_paste_previews!(
+        r#"
+            s.score AS "sort_key!",
+            NULL::TIMESTAMPTZ AS starred_at
+        FROM
+            websearch_to_tsquery('simple', $1) AS query(q)
+        CROSS JOIN LATERAL (
+            SELECT pastes.id FROM pastes
+            WHERE paste_search_vector(pastes.name, pastes.description) @@ query.q
+            UNION
+            SELECT files.paste_id FROM files
+            WHERE file_search_vector(files.filename, files.content) @@ query.q
+        ) AS candidates
+        JOIN pastes ON pastes.id = candidates.id
+        "#,
+        r#"
+        LEFT JOIN LATERAL (
+            SELECT MAX(ts_rank(file_search_vector(filename, content), query.q)) AS rank
+            FROM files
+            WHERE
+                files.paste_id = pastes.id
+                AND file_search_vector(filename, content) @@ query.q
+        ) AS m ON true
+        CROSS JOIN LATERAL (
+            SELECT ((
+                ts_rank(paste_search_vector(pastes.name, pastes.description), query.q)
+                + COALESCE(m.rank, 0)
+            ) * 1000000)::BIGINT AS score
+        ) AS s
+        WHERE
+            ((visibility = 3 AND NOT burn_after_read) OR author_id = $2)
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            AND ($3::TEXT IS NULL OR EXISTS(
+                SELECT 1 FROM files
+                WHERE files.paste_id = pastes.id AND LOWER(files.language) = LOWER($3)
+            ))
+            AND ($4::TEXT IS NULL OR author_id = $4)
+            AND ($5::BIGINT IS NULL OR (s.score, pastes.id) < ($5, $6))
+        ORDER BY s.score DESC, pastes.id DESC
+        LIMIT $7
+        "#,
+        q,
+        auth.as_ref().map(|Authorization(a)| a),
+        query.language,
+        query.author,
+        pagination.key(),
+        pagination.id(),
+        pagination.fetch_limit(),
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    let page = pagination.paginate(results, None, |result| Cursor {
+        key: result.sort_key,
+        id: result.id.clone(),
+    });
+
+    // Headlines are expensive, so they are only generated for the best matching file of each
+    // paste on this page
+    let paste_ids = page
+        .items
+        .iter()
+        .map(|result| result.id.clone())
+        .collect::<Vec<_>>();
+
+    let mut snippets = sqlx::query!(
+        r#"
+        SELECT
+            page.id AS "paste_id!",
+            m.idx AS "idx!",
+            ts_headline(
+                'simple',
+                TRANSLATE(LEFT(m.content, 100000), CHR(2) || CHR(3), ''),
+                query.q,
+                'StartSel=' || CHR(2) || ', StopSel=' || CHR(3)
+                    || ', MaxFragments=1, MaxWords=32, MinWords=12'
+            ) AS "headline!"
+        FROM
+            websearch_to_tsquery('simple', $1) AS query(q),
+            UNNEST($2::TEXT[]) AS page(id)
+        CROSS JOIN LATERAL (
+            SELECT idx, content
+            FROM files
+            WHERE
+                files.paste_id = page.id
+                AND file_search_vector(filename, content) @@ query.q
+            ORDER BY ts_rank(file_search_vector(filename, content), query.q) DESC, idx ASC
+            LIMIT 1
+        ) AS m
+        "#,
+        q,
+        &paste_ids,
+    )
+    .fetch_all(get_pool())
+    .await?
+    .into_iter()
+    .map(|record| {
+        let (content, highlights) = parse_headline(&record.headline);
+
+        (
+            record.paste_id,
+            Snippet {
+                file_idx: record.idx as u16,
+                content,
+                highlights,
+            },
+        )
+    })
+    .collect::<HashMap<_, _>>();
+
+    Ok(JsonResponse::ok(page.map(|result| SearchResult {
+        snippet: snippets.remove(&result.id),
+        paste: PastePreview::from(result),
+    })))
+}
+
+pub fn router() -> Router {
+    Router::new().route("/pastes/search", get(search_pastes.layer(ratelimit!(5, 5))))
+}
@@ -0,0 +1,177 @@
+use super::{AuthorizedToken, ClientInfo, JsonResponse};
+use crate::{
+    auth::{generate_id, generate_token, token_created_at},
+    cache::{evict_tokens, tokens_last_used},
+    get_pool,
+    json::Error,
+};
+
+use axum::{
+    extract::{Path, Query},
+    handler::Handler,
+    http::StatusCode,
+    routing::{delete, get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+#[derive(Debug, Serialize)]
+pub struct Session {
+    pub id: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// Whether this is the session the request was made with.
+    pub current: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeSessionsQuery {
+    #[serde(default)]
+    pub except_current: bool,
+}
+
+/// Generates a new token for the user and stores it as a session, returning the token.
+pub async fn create_session(
+    user_id: &str,
+    client: &ClientInfo,
+) -> Result<String, JsonResponse<Error>> {
+    let token = generate_token(user_id.to_string());
+
+    sqlx::query!(
+        "INSERT INTO tokens (id, user_id, token, user_agent, ip) VALUES ($1, $2, $3, $4, $5)",
+        generate_id::<12>(),
+        user_id,
+        token,
+        client.user_agent,
+        client.ip,
+    )
+    .execute(get_pool())
+    .await?;
+
+    Ok(token)
+}
+
+/// GET /users/me/sessions
+///
+/// # Note
+/// Sessions are ordered by when they were created, most recent first.
+pub async fn list_sessions(
+    AuthorizedToken { user_id, token }: AuthorizedToken,
+) -> Result<JsonResponse<Vec<Session>>, JsonResponse<Error>> {
+    let records = sqlx::query!(
+        "SELECT id, token, last_used_at, user_agent, ip FROM tokens WHERE user_id = $1",
+        user_id,
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    let tokens = records
+        .iter()
+        .map(|record| record.token.clone())
+        .collect::<Vec<_>>();
+    let last_used = tokens_last_used(&tokens).await?;
+
+    let mut sessions = records
+        .into_iter()
+        .zip(last_used)
+        .map(|(record, last_used)| Session {
+            id: record.id,
+            // Every token we generate has a valid epoch
+            created_at: token_created_at(&record.token).map_or(0, |ms| (ms / 1000) as i64),
+            last_used_at: record
+                .last_used_at
+                .map(|last_used_at| last_used_at.timestamp())
+                .max(last_used),
+            user_agent: record.user_agent,
+            ip: record.ip,
+            current: record.token == token,
+        })
+        .collect::<Vec<_>>();
+
+    sessions.sort_by_key(|session| Reverse(session.created_at));
+
+    Ok(JsonResponse::ok(sessions))
+}
+
+/// DELETE /users/me/sessions/:id
+pub async fn revoke_session(
+    AuthorizedToken { user_id, .. }: AuthorizedToken,
+    Path(id): Path<String>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    let token = sqlx::query!(
+        "DELETE FROM tokens WHERE id = $1 AND user_id = $2 RETURNING token",
+        id,
+        user_id,
+    )
+    .fetch_optional(get_pool())
+    .await?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Error {
+                message: "Session not found".to_string(),
+            },
+        )
+    })?
+    .token;
+
+    evict_tokens(&[token]).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /users/me/sessions
+///
+/// # Note
+/// This logs out everywhere, including the current session unless `except_current` is set.
+pub async fn revoke_sessions(
+    AuthorizedToken { user_id, token }: AuthorizedToken,
+    Query(RevokeSessionsQuery { except_current }): Query<RevokeSessionsQuery>,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    let except = except_current.then_some(token);
+
+    let tokens = sqlx::query!(
+        "DELETE FROM tokens WHERE user_id = $1 AND token IS DISTINCT FROM $2 RETURNING token",
+        user_id,
+        except,
+    )
+    .fetch_all(get_pool())
+    .await?
+    .into_iter()
+    .map(|record| record.token)
+    .collect::<Vec<_>>();
+
+    evict_tokens(&tokens).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /logout
+pub async fn logout(
+    AuthorizedToken { token, .. }: AuthorizedToken,
+) -> Result<StatusCode, JsonResponse<Error>> {
+    sqlx::query!("DELETE FROM tokens WHERE token = $1", token)
+        .execute(get_pool())
+        .await?;
+
+    evict_tokens(&[token]).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/users/me/sessions",
+            get(list_sessions.layer(ratelimit!(5, 5)))
+                .delete(revoke_sessions.layer(ratelimit!(2, 10))),
+        )
+        .route(
+            "/users/me/sessions/:id",
+            delete(revoke_session.layer(ratelimit!(5, 5))),
+        )
+        .route("/logout", post(logout.layer(ratelimit!(5, 5))))
+}
@@ -0,0 +1,165 @@
+use super::{Authorization, JsonResponse};
+use crate::{
+    get_pool,
+    json::Error,
+    pagination::{Cursor, Page, Pagination, DEFAULT_LIMIT, MAX_LIMIT},
+    routes::pastes::PastePreview,
+};
+
+use axum::{
+    extract::{Path, Query},
+    handler::Handler,
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct TagPastesQuery {
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct PopularTagsQuery {
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u32,
+}
+
+/// GET /tags/:tag/pastes
+///
+/// # Limits
+/// - At most 100 pastes per page
+///
+/// # Note
+/// Only discoverable pastes are listed, along with the authorized user's own pastes.
+pub async fn get_tag_pastes(
+    auth: Option<Authorization>,
+    Path(tag): Path<String>,
+    Query(query): Query<TagPastesQuery>,
+) -> Result<JsonResponse<Page<PastePreview>>, JsonResponse<Error>> {
+    // Tags are always stored in lowercase
+    let tag = tag.to_lowercase();
+    let pagination = Pagination::new(
+        format!("tags.{}", tag),
+        query.before,
+        query.after,
+        query.limit,
+    )?;
+    let user_id = auth.as_ref().map(|Authorization(a)| a);
+    let db = get_pool();
+
+    let total = sqlx::query!(
+        "
+        SELECT COUNT(*) AS count FROM paste_tags
+        JOIN pastes ON pastes.id = paste_tags.paste_id
+        WHERE
+            tag = $1
+            AND ((visibility = 3 AND NOT burn_after_read) OR author_id = $2)
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+    ",
+        tag,
+        user_id,
+    )
+    .fetch_one(db)
+    .await?
+    .count
+    .unwrap_or(0);
+
+    let pastes = query_paste_previews!(
+        r#"
+            s.key AS "sort_key!",
+            NULL::TIMESTAMPTZ AS starred_at
+        FROM
+            paste_tags
+        JOIN pastes ON pastes.id = paste_tags.paste_id
+        "#,
+        r#"
+        CROSS JOIN LATERAL (
+            SELECT (EXTRACT(EPOCH FROM pastes.created_at) * 1000000)::BIGINT AS key
+        ) AS s
+        WHERE
+            tag = $1
+            AND ((visibility = 3 AND NOT burn_after_read) OR author_id = $2)
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            AND ($3::BIGINT IS NULL OR CASE
+                WHEN $5 THEN (s.key, pastes.id) > ($3, $4)
+                ELSE (s.key, pastes.id) < ($3, $4)
+            END)
+        ORDER BY
+            CASE WHEN $5 THEN s.key END ASC,
+            CASE WHEN $5 THEN pastes.id END ASC,
+            s.key DESC,
+            pastes.id DESC
+        LIMIT $6
+        "#,
+        tag,
+        user_id,
+        pagination.key(),
+        pagination.id(),
+        pagination.backwards,
+        pagination.fetch_limit(),
+    )
+    .fetch_all(db)
+    .await?;
+
+    let page = pagination.paginate(pastes, Some(total as u32), |paste| Cursor {
+        key: paste.sort_key,
+        id: paste.id.clone(),
+    });
+
+    Ok(JsonResponse::ok(page.map(PastePreview::from)))
+}
+
+/// GET /tags/popular
+///
+/// # Note
+/// Tags are ranked by the amount of discoverable pastes using them.
+pub async fn get_popular_tags(
+    Query(query): Query<PopularTagsQuery>,
+) -> Result<JsonResponse<Vec<TagCount>>, JsonResponse<Error>> {
+    let tags = sqlx::query!(
+        r#"
+        SELECT tag, COUNT(*) AS "count!"
+        FROM paste_tags
+        JOIN pastes ON pastes.id = paste_tags.paste_id
+        WHERE
+            visibility = 3
+            AND NOT burn_after_read
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        GROUP BY tag
+        ORDER BY COUNT(*) DESC, tag ASC
+        LIMIT $1
+        "#,
+        query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as i64,
+    )
+    .fetch_all(get_pool())
+    .await?;
+
+    Ok(JsonResponse::ok(
+        tags.into_iter()
+            .map(|record| TagCount {
+                tag: record.tag,
+                count: record.count as u32,
+            })
+            .collect(),
+    ))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/tags/popular",
+            get(get_popular_tags.layer(ratelimit!(5, 5))),
+        )
+        .route(
+            "/tags/:tag/pastes",
+            get(get_tag_pastes.layer(ratelimit!(5, 5))),
+        )
+}
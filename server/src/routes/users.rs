@@ -1,17 +1,17 @@
-use super::{Authorization, JsonResponse};
+use super::{sessions::create_session, Authorization, ClientInfo, JsonResponse};
 use crate::{
-    auth::{generate_id, generate_token},
+    auth::generate_id,
+    cache::evict_tokens,
     get_pool,
     json::Error,
     oauth::{get_github_info, get_github_user},
+    pagination::{Cursor, Page, Pagination},
     routes::pastes::{File, PastePreview, PasteVisibility},
-    RatelimitLayer,
 };
 
 use argon2_async::{hash, verify};
 use axum::{
-    error_handling::HandleErrorLayer,
-    extract::{Json, Path},
+    extract::{Json, Path, Query},
     handler::Handler,
     http::StatusCode,
     routing::{get, post, MethodFilter},
@@ -20,7 +20,6 @@ use axum::{
 use check_if_email_exists::{check_email, CheckEmailInput, Reachable};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tower::{buffer::BufferLayer, ServiceBuilder};
 
 pub type Timestamp = DateTime<Utc>;
 
@@ -196,6 +195,7 @@ fn validate_username(username: &str) -> Result<(), JsonResponse<Error>> {
 /// - Password between 6 and 128 characters
 /// - Email must be unique and valid
 pub async fn create_user(
+    client: ClientInfo,
     Json(UserPayload {
         username,
         email,
@@ -272,13 +272,12 @@ pub async fn create_user(
     .execute(db)
     .await?;
 
-    let token = generate_token(id.clone());
+    let token = create_session(&id, &client).await?;
 
-    sqlx::query!("INSERT INTO tokens VALUES ($1, $2)", id, token)
-        .execute(db)
-        .await?;
-
-    Ok(JsonResponse(StatusCode::CREATED, UserCreateResponse { id, token }))
+    Ok(JsonResponse(
+        StatusCode::CREATED,
+        UserCreateResponse { id, token },
+    ))
 }
 
 /// POST /users/github
@@ -288,6 +287,7 @@ pub async fn create_user(
 /// - Username between 3 to 32 characters and unique
 /// - GitHub email must be verified
 pub async fn create_user_github(
+    client: ClientInfo,
     Json(GithubUserPayload {
         username,
         access_code,
@@ -345,13 +345,12 @@ pub async fn create_user_github(
     .execute(db)
     .await?;
 
-    let token = generate_token(id.clone());
-
-    sqlx::query!("INSERT INTO tokens VALUES ($1, $2)", id, token)
-        .execute(db)
-        .await?;
+    let token = create_session(&id, &client).await?;
 
-    Ok(JsonResponse(StatusCode::CREATED, UserCreateResponse { id, token }))
+    Ok(JsonResponse(
+        StatusCode::CREATED,
+        UserCreateResponse { id, token },
+    ))
 }
 
 /// POST /login
@@ -360,6 +359,7 @@ pub async fn create_user_github(
 /// - 1 request per 20 seconds
 /// - Supports either email or username
 pub async fn login(
+    client: ClientInfo,
     Json(LoginPayload {
         username,
         email,
@@ -427,11 +427,7 @@ pub async fn login(
         ));
     }
 
-    let token = generate_token(id.clone());
-
-    sqlx::query!("INSERT INTO tokens VALUES ($1, $2)", id, token)
-        .execute(db)
-        .await?;
+    let token = create_session(&id, &client).await?;
 
     Ok(JsonResponse::ok(LoginResponse { id, token }))
 }
@@ -441,6 +437,7 @@ pub async fn login(
 /// # Limits
 /// - 1 request per 20 seconds
 pub async fn login_github(
+    client: ClientInfo,
     Json(GithubLoginPayload { access_code }): Json<GithubLoginPayload>,
 ) -> Result<JsonResponse<LoginResponse>, JsonResponse<Error>> {
     let github_data = get_github_user(access_code).await?;
@@ -462,11 +459,7 @@ pub async fn login_github(
     })?
     .id;
 
-    let token = generate_token(id.clone());
-
-    sqlx::query!("INSERT INTO tokens VALUES ($1, $2)", id, token)
-        .execute(db)
-        .await?;
+    let token = create_session(&id, &client).await?;
 
     Ok(JsonResponse::ok(LoginResponse { id, token }))
 }
@@ -477,14 +470,73 @@ pub async fn delete_user(
 ) -> Result<StatusCode, JsonResponse<Error>> {
     let db = get_pool();
 
+    // Tokens are also deleted along with the user, but they have to be evicted from the cache
+    let tokens = sqlx::query!(
+        "DELETE FROM tokens WHERE user_id = $1 RETURNING token",
+        user_id,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|record| record.token)
+    .collect::<Vec<_>>();
+
     sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
         .execute(db)
         .await?;
 
+    evict_tokens(&tokens).await?;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
-fn into_sanitized_paste(
+/// A paste along with its author and first file, as selected by `query_paste_previews!`.
+pub struct PastePreviewRow {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub author_id: Option<String>,
+    pub author_name: Option<String>,
+    pub visibility: i16,
+    pub created_at: Timestamp,
+    pub views: i32,
+    pub expires_at: Option<Timestamp>,
+    pub burn_after_read: bool,
+    pub filename: Option<String>,
+    pub content: String,
+    pub language: Option<String>,
+    pub stars: i64,
+    // The key the rows are ordered and paginated by
+    pub sort_key: i64,
+    pub starred_at: Option<Timestamp>,
+}
+
+impl From<PastePreviewRow> for PastePreview {
+    fn from(row: PastePreviewRow) -> Self {
+        Self {
+            id: Some(row.id),
+            name: Some(row.name),
+            description: row.description,
+            author_id: row.author_id,
+            author_name: row.author_name,
+            created_at: row.created_at.timestamp(),
+            visibility: PasteVisibility::from(row.visibility as u8),
+            stars: row.stars as u32,
+            views: row.views as u32,
+            first_file: Some(File {
+                filename: row.filename,
+                content: row.content,
+                language: row.language,
+            }),
+            available: true,
+            starred_at: row.starred_at.map(|s| s.timestamp()),
+            expires_at: row.expires_at.map(|e| e.timestamp()),
+            burn_after_read: row.burn_after_read,
+        }
+    }
+}
+
+pub fn into_sanitized_paste(
     PastePreview {
         author_id,
         author_name,
@@ -493,6 +545,8 @@ fn into_sanitized_paste(
         stars,
         views,
         starred_at,
+        expires_at,
+        burn_after_read,
         ..
     }: PastePreview,
 ) -> PastePreview {
@@ -505,6 +559,8 @@ fn into_sanitized_paste(
         stars,
         views,
         starred_at,
+        expires_at,
+        burn_after_read,
         available: false,
         name: None,
         description: None,
@@ -514,7 +570,8 @@ fn into_sanitized_paste(
 
 pub fn sanitize_paste(auth: &Option<Authorization>, preview: PastePreview) -> PastePreview {
     if let Some(Authorization(user_id)) = auth {
-        if preview.visibility == PasteVisibility::Discoverable {
+        // The first file of a burn-after-read paste would otherwise be readable without burning it
+        if preview.visibility == PasteVisibility::Discoverable && !preview.burn_after_read {
             return preview;
         } else if let Some(owner_id) = &preview.author_id {
             if user_id == owner_id {
@@ -526,129 +583,212 @@ pub fn sanitize_paste(auth: &Option<Authorization>, preview: PastePreview) -> Pa
     into_sanitized_paste(preview)
 }
 
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteListSort {
+    Created,
+    Stars,
+    Views,
+    // Only available when listing stars
+    StarredAt,
+}
+
+impl PasteListSort {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Stars => "stars",
+            Self::Views => "views",
+            Self::StarredAt => "starred_at",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PasteListQuery {
+    pub sort: Option<PasteListSort>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub limit: Option<u32>,
+}
+
 /// GET /users/:uesr_id/pastes
+///
+/// # Limits
+/// - At most 100 pastes per page
 pub async fn list_user_pastes(
     auth: Option<Authorization>,
     Path(user_id): Path<String>,
-) -> Result<JsonResponse<Vec<PastePreview>>, JsonResponse<Error>> {
+    Query(query): Query<PasteListQuery>,
+) -> Result<JsonResponse<Page<PastePreview>>, JsonResponse<Error>> {
+    let sort = query.sort.unwrap_or(PasteListSort::Created);
+    if sort == PasteListSort::StarredAt {
+        return Err(JsonResponse(
+            StatusCode::BAD_REQUEST,
+            Error {
+                message: "Pastes can only be sorted by starred_at when listing stars".to_string(),
+            },
+        ));
+    }
+
+    let pagination = Pagination::new(
+        format!("pastes.{}", sort.as_str()),
+        query.before,
+        query.after,
+        query.limit,
+    )?;
     let db = get_pool();
 
-    let pastes = sqlx::query!(
+    let total = sqlx::query!(
+        "
+        SELECT COUNT(*) AS count FROM pastes
+        WHERE author_id = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+    ",
+        user_id,
+    )
+    .fetch_one(db)
+    .await?
+    .count
+    .unwrap_or(0);
+
+    let pastes = query_paste_previews!(
         r#"
-        SELECT
-            pastes.*,
-            u.username AS "username?",
-            f.filename AS "filename?",
-            f.content AS "content!",
-            f.language AS "language?",
-            (SELECT COUNT(*) FROM stars WHERE paste_id = pastes.id) AS stars
+            s.key AS "sort_key!",
+            NULL::TIMESTAMPTZ AS starred_at
         FROM
             pastes
-        LEFT JOIN LATERAL (
-            SELECT username FROM users WHERE users.id = pastes.author_id
-        ) AS u ON username IS NOT NULL
-        LEFT JOIN LATERAL (
-            SELECT * FROM files WHERE files.paste_id = pastes.id AND files.idx = 0
-        ) AS f ON true
+        "#,
+        r#"
+        CROSS JOIN LATERAL (
+            SELECT CASE $2
+                WHEN 'stars' THEN (SELECT COUNT(*) FROM stars WHERE paste_id = pastes.id)
+                WHEN 'views' THEN pastes.views::BIGINT
+                ELSE (EXTRACT(EPOCH FROM pastes.created_at) * 1000000)::BIGINT
+            END AS key
+        ) AS s
         WHERE
-            author_id = $1
+            author_id = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            AND ($3::BIGINT IS NULL OR CASE
+                WHEN $5 THEN (s.key, pastes.id) > ($3, $4)
+                ELSE (s.key, pastes.id) < ($3, $4)
+            END)
+        ORDER BY
+            CASE WHEN $5 THEN s.key END ASC,
+            CASE WHEN $5 THEN pastes.id END ASC,
+            s.key DESC,
+            pastes.id DESC
+        LIMIT $6
         "#,
         user_id,
+        sort.as_str(),
+        pagination.key(),
+        pagination.id(),
+        pagination.backwards,
+        pagination.fetch_limit(),
     )
     .fetch_all(db)
     .await?;
 
+    let page = pagination.paginate(pastes, Some(total as u32), |paste| Cursor {
+        key: paste.sort_key,
+        id: paste.id.clone(),
+    });
+
     Ok(JsonResponse::ok(
-        pastes
-            .into_iter()
-            .map(|paste| PastePreview {
-                id: Some(paste.id),
-                name: Some(paste.name),
-                description: paste.description,
-                author_id: paste.author_id,
-                author_name: paste.username,
-                created_at: paste.created_at.timestamp(),
-                visibility: PasteVisibility::from(paste.visibility as u8),
-                stars: paste.stars.unwrap_or(0) as u32,
-                views: paste.views as u32,
-                first_file: Some(File {
-                    filename: paste.filename,
-                    content: paste.content,
-                    language: paste.language,
-                }),
-                available: true,
-                starred_at: None,
-            })
-            .map(|paste| sanitize_paste(&auth, paste))
-            .collect(),
+        page.map(PastePreview::from)
+            .map(|paste| sanitize_paste(&auth, paste)),
     ))
 }
 
 /// GET /users/:user_id/stars
+///
+/// # Limits
+/// - At most 100 pastes per page
 pub async fn list_user_stars(
     auth: Option<Authorization>,
     Path(user_id): Path<String>,
-) -> Result<JsonResponse<Vec<PastePreview>>, JsonResponse<Error>> {
+    Query(query): Query<PasteListQuery>,
+) -> Result<JsonResponse<Page<PastePreview>>, JsonResponse<Error>> {
+    let sort = query.sort.unwrap_or(PasteListSort::StarredAt);
+    let pagination = Pagination::new(
+        format!("stars.{}", sort.as_str()),
+        query.before,
+        query.after,
+        query.limit,
+    )?;
     let db = get_pool();
 
-    let stars = sqlx::query!(
+    let total = sqlx::query!(
+        "
+        SELECT COUNT(*) AS count FROM stars
+        JOIN pastes ON pastes.id = stars.paste_id
+        WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+    ",
+        user_id,
+    )
+    .fetch_one(db)
+    .await?
+    .count
+    .unwrap_or(0);
+
+    let stars = query_paste_previews!(
         r#"
-        SELECT
-            pastes.*,
-            u.username AS "username?",
-            f.filename AS "filename?",
-            f.content AS "content!",
-            f.language AS "language?",
-            (SELECT COUNT(*) FROM stars WHERE paste_id = pastes.id) AS stars,
-            (SELECT created_at FROM stars WHERE paste_id = pastes.id AND user_id = $1) AS starred_at
+            s.key AS "sort_key!",
+            star.created_at AS "starred_at?"
         FROM
-            pastes
-        LEFT JOIN LATERAL (
-            SELECT username FROM users WHERE users.id = pastes.author_id
-        ) AS u ON username IS NOT NULL
-        LEFT JOIN LATERAL (
-            SELECT * FROM files WHERE files.paste_id = pastes.id AND files.idx = 0
-        ) AS f ON true
+            stars AS star
+        JOIN pastes ON pastes.id = star.paste_id
+        "#,
+        r#"
+        CROSS JOIN LATERAL (
+            SELECT CASE $2
+                WHEN 'created' THEN (EXTRACT(EPOCH FROM pastes.created_at) * 1000000)::BIGINT
+                WHEN 'stars' THEN (SELECT COUNT(*) FROM stars WHERE paste_id = pastes.id)
+                WHEN 'views' THEN pastes.views::BIGINT
+                ELSE (EXTRACT(EPOCH FROM star.created_at) * 1000000)::BIGINT
+            END AS key
+        ) AS s
         WHERE
-            id
-        IN (SELECT paste_id FROM stars WHERE user_id = $1)
+            star.user_id = $1
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            AND ($3::BIGINT IS NULL OR CASE
+                WHEN $5 THEN (s.key, pastes.id) > ($3, $4)
+                ELSE (s.key, pastes.id) < ($3, $4)
+            END)
+        ORDER BY
+            CASE WHEN $5 THEN s.key END ASC,
+            CASE WHEN $5 THEN pastes.id END ASC,
+            s.key DESC,
+            pastes.id DESC
+        LIMIT $6
         "#,
         user_id,
+        sort.as_str(),
+        pagination.key(),
+        pagination.id(),
+        pagination.backwards,
+        pagination.fetch_limit(),
     )
     .fetch_all(db)
     .await?;
 
+    let page = pagination.paginate(stars, Some(total as u32), |paste| Cursor {
+        key: paste.sort_key,
+        id: paste.id.clone(),
+    });
+
     Ok(JsonResponse::ok(
-        stars
-            .into_iter()
-            .map(|paste| PastePreview {
-                id: Some(paste.id),
-                name: Some(paste.name),
-                description: paste.description,
-                author_id: paste.author_id,
-                author_name: paste.username,
-                created_at: paste.created_at.timestamp(),
-                visibility: PasteVisibility::from(paste.visibility as u8),
-                stars: paste.stars.unwrap_or(0) as u32,
-                views: paste.views as u32,
-                first_file: Some(File {
-                    filename: paste.filename,
-                    content: paste.content,
-                    language: paste.language,
-                }),
-                available: true,
-                starred_at: paste.starred_at.map(|s| s.timestamp()),
-            })
-            .map(|paste| sanitize_paste(&auth, paste))
-            .collect(),
+        page.map(PastePreview::from)
+            .map(|paste| sanitize_paste(&auth, paste)),
     ))
 }
 
 /// GET /users/me/stars
 pub async fn list_self_stars(
     auth: Authorization,
-) -> Result<JsonResponse<Vec<PastePreview>>, JsonResponse<Error>> {
-    list_user_stars(Some(auth.clone()), Path(auth.0)).await
+    query: Query<PasteListQuery>,
+) -> Result<JsonResponse<Page<PastePreview>>, JsonResponse<Error>> {
+    list_user_stars(Some(auth.clone()), Path(auth.0), query).await
 }
 
 /// GET /pastes/:paste_id/stars
@@ -787,22 +927,6 @@ pub async fn validate(
     Ok(StatusCode::NO_CONTENT)
 }
 
-macro_rules! ratelimit {
-    ($rate:expr, $per:expr) => {{
-        ServiceBuilder::new()
-            .layer(HandleErrorLayer::new(|e| async move {
-                JsonResponse(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Error {
-                        message: format!("Internal error: {}", e),
-                    },
-                )
-            }))
-            .layer(BufferLayer::new(1024))
-            .layer(RatelimitLayer($rate, $per))
-    }};
-}
-
 pub fn router() -> Router {
     Router::new()
         .route("/users/validate", post(validate.layer(ratelimit!(6, 6))))
@@ -0,0 +1,196 @@
+use crate::{
+    json::{Error, JsonResponse},
+    routes::pastes::{File, PastePayload, PasteVisibility, MAX_FILES, MAX_FILE_SIZE},
+};
+
+use axum::{
+    async_trait,
+    body::Body,
+    extract::{multipart::Field, FromRequest, Json, Multipart, RequestParts},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// The maximum size of a metadata field such as the name or description of a paste in bytes.
+const MAX_FIELD_SIZE: usize = 16 * 1024;
+
+/// The body of a POST /pastes request, which is either JSON or `multipart/form-data`.
+pub struct PasteBody(pub PastePayload);
+
+#[async_trait]
+impl FromRequest<Body> for PasteBody {
+    type Rejection = Response;
+
+    async fn from_request(req: &mut RequestParts<Body>) -> Result<Self, Self::Rejection> {
+        let is_multipart = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("multipart/form-data"));
+
+        if !is_multipart {
+            let Json(payload) = Json::from_request(req)
+                .await
+                .map_err(IntoResponse::into_response)?;
+
+            return Ok(Self(payload));
+        }
+
+        let multipart = Multipart::from_request(req)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        read_multipart(multipart)
+            .await
+            .map(Self)
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+fn bad_request(message: String) -> JsonResponse<Error> {
+    JsonResponse(StatusCode::BAD_REQUEST, Error { message })
+}
+
+/// Reads a field chunk by chunk, so that oversized fields are rejected without buffering them.
+async fn read_field(
+    field: &mut Field<'_>,
+    max_size: usize,
+    too_large: impl FnOnce() -> JsonResponse<Error>,
+) -> Result<String, JsonResponse<Error>> {
+    let mut buffer = Vec::new();
+
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| bad_request(e.to_string()))?
+    {
+        if buffer.len() + chunk.len() > max_size {
+            return Err(too_large());
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(buffer).map_err(|_| {
+        bad_request(format!(
+            "The {:?} field is not valid UTF-8",
+            field.name().unwrap_or_default(),
+        ))
+    })
+}
+
+fn parse_field<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, JsonResponse<Error>> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| bad_request(format!("Invalid value for the {:?} field", name)))
+}
+
+/// Converts a `multipart/form-data` body into a paste payload.
+///
+/// Every part with a filename, or named `file` or `files`, becomes a file of the paste in the order
+/// they are sent. The other fields of [`PastePayload`] can be sent as regular form fields.
+///
+/// There is no way to set the language of a file sent this way. Languages are instead inferred
+/// from the filename and content when the paste is created, see
+/// [`crate::routes::pastes::resolve_languages`].
+pub async fn read_multipart(mut multipart: Multipart) -> Result<PastePayload, JsonResponse<Error>> {
+    let mut payload = PastePayload {
+        name: None,
+        description: None,
+        visibility: PasteVisibility::default(),
+        password: None,
+        files: Vec::new(),
+        expires_in: None,
+        expires_at: None,
+        burn_after_read: false,
+        tags: Vec::new(),
+    };
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| bad_request(e.to_string()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+
+        if field.file_name().is_some() || name == "file" || name == "files" {
+            let idx = payload.files.len();
+            if idx >= MAX_FILES {
+                return Err(bad_request(format!(
+                    "Received more than {} files, which is the maximum",
+                    MAX_FILES,
+                )));
+            }
+
+            // Browsers send an empty filename for files that weren't given one
+            let filename = field
+                .file_name()
+                .filter(|filename| !filename.is_empty())
+                .map(ToString::to_string);
+            let content = read_field(&mut field, MAX_FILE_SIZE, || {
+                JsonResponse(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    Error {
+                        message: format!(
+                            "The file at index {} (0-indexed) surpasses the maximum of {} MiB",
+                            idx,
+                            MAX_FILE_SIZE / 1024 / 1024,
+                        ),
+                    },
+                )
+            })
+            .await?;
+
+            payload.files.push(File {
+                filename,
+                content,
+                // Parts can't carry a language, see `read_multipart`
+                language: None,
+            });
+            continue;
+        }
+
+        let value = read_field(&mut field, MAX_FIELD_SIZE, || {
+            JsonResponse(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Error {
+                    message: format!("The {:?} field is too large", name),
+                },
+            )
+        })
+        .await?;
+
+        match name.as_str() {
+            "name" => payload.name = Some(value),
+            "description" => payload.description = Some(value),
+            "password" => payload.password = Some(value),
+            // Tags can be sent as multiple fields, or comma separated in a single field
+            "tags" => payload
+                .tags
+                .extend(value.split(',').map(|tag| tag.trim().to_string())),
+            "visibility" => {
+                payload.visibility = match parse_field::<u8>(&name, &value)? {
+                    n @ 0..=3 => PasteVisibility::from(n),
+                    _ => return Err(bad_request("Invalid paste visibility".to_string())),
+                }
+            }
+            "expires_in" => payload.expires_in = Some(parse_field(&name, &value)?),
+            "expires_at" => payload.expires_at = Some(parse_field(&name, &value)?),
+            "burn_after_read" => {
+                payload.burn_after_read = match value.trim() {
+                    "true" | "on" | "1" => true,
+                    "false" | "off" | "0" => false,
+                    _ => {
+                        return Err(bad_request(format!(
+                            "Invalid value for the {:?} field",
+                            name
+                        )))
+                    }
+                }
+            }
+            _ => return Err(bad_request(format!("Unknown field {:?}", name))),
+        }
+    }
+
+    Ok(payload)
+}